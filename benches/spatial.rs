@@ -0,0 +1,76 @@
+//! Compares the spatial hash against testing every pair of entities.
+//! Run with `cargo bench --bench spatial`
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[path = "../src/spatial.rs"]
+#[allow(dead_code)]
+mod spatial;
+
+use spatial::SpatialHash;
+
+/// Matches the cell size used by the game's spatial grid
+const CELL_SIZE: f32 = 128.0;
+/// Typical aggro range
+const QUERY_RADIUS: f32 = 150.0;
+/// Entities per square pixel, kept constant so that only the world grows
+const DENSITY: f32 = 1.0 / (64.0 * 64.0);
+
+fn positions(count: usize) -> Vec<(Entity, Vec2)> {
+    let side = (count as f32 / DENSITY).sqrt();
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..count)
+        .map(|i| {
+            let position = Vec2::new(rng.gen_range(0.0..side), rng.gen_range(0.0..side));
+            (Entity::from_raw(i as u32), position)
+        })
+        .collect()
+}
+
+/// One frame: every entity looks for its neighbours
+fn spatial_hash(hash: &mut SpatialHash, entities: &[(Entity, Vec2)]) -> usize {
+    hash.clear();
+    for (entity, position) in entities {
+        hash.insert(*entity, *position);
+    }
+    entities
+        .iter()
+        .map(|(_, position)| hash.query_radius(*position, QUERY_RADIUS).count())
+        .sum()
+}
+
+fn brute_force(entities: &[(Entity, Vec2)]) -> usize {
+    entities
+        .iter()
+        .map(|(_, position)| {
+            entities
+                .iter()
+                .filter(|(_, other)| {
+                    other.distance_squared(*position) <= QUERY_RADIUS * QUERY_RADIUS
+                })
+                .count()
+        })
+        .sum()
+}
+
+fn neighbour_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("neighbour_queries");
+    for count in [500, 1000, 5000] {
+        let entities = positions(count);
+        let mut hash = SpatialHash::new(CELL_SIZE);
+        group.bench_with_input(
+            BenchmarkId::new("spatial_hash", count),
+            &entities,
+            |b, e| b.iter(|| spatial_hash(&mut hash, e)),
+        );
+        group.bench_with_input(BenchmarkId::new("brute_force", count), &entities, |b, e| {
+            b.iter(|| brute_force(e))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, neighbour_queries);
+criterion_main!(benches);
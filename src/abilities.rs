@@ -1,15 +1,26 @@
-use crate::damage::{CriticalHit, Damage, DamageEvent, DisplayDamageNumbersEvent};
-use crate::enemy::Enemy;
-use crate::entities::{DespawnTimer, Facing, FrameAnimation};
+use crate::collision::{
+    detect_collisions, Collider, CollisionEvent, ENEMY_LAYER, PLAYER_LAYER, PROJECTILE_LAYER,
+};
+use crate::damage::{CriticalHit, Damage, DamageEvent, DamageType};
+use crate::effects::{ApplyEffectEvent, OnHitEffect};
+use crate::entities::{Dead, DespawnTimer, FrameAnimation, Health};
+use crate::faction::{Faction, FactionRelations};
+use crate::kinematics::Velocity;
+use crate::tilemap::TileMap;
 use crate::TILE_SIZE;
 use bevy::prelude::*;
-use rand::Rng;
+use bevy::utils::HashSet;
+use serde::Deserialize;
 
 const COLUMNS: usize = 8;
 const ROWS: usize = 8;
 
 const FIREBALL_FRAMES: usize = 7;
 pub const FIREBALL_BASE_DAMAGE: f32 = 28.0;
+/// Time an arrow flies before despawning, in seconds
+const ARROW_LIFETIME: f32 = 3.0;
+const ARROW_SIZE: Vec2 = Vec2::new(20.0, 3.0);
+const ARROW_COLOR: Color = Color::rgb(0.55, 0.35, 0.2);
 
 #[derive(Resource)]
 pub struct AbilitySheet {
@@ -29,22 +40,65 @@ pub struct AbilityPlugin;
 
 impl Plugin for AbilityPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_abilities)
+        app.register_type::<AbilityKind>()
+            .add_systems(Startup, load_abilities)
             .add_systems(Update, animate_fireball)
-            .add_systems(Update, projectile_mouvement)
-            .add_systems(Update, projectile_collision)
-            .add_systems(Update, display_damage_numbers);
+            .add_systems(Update, projectile_hits.after(detect_collisions))
+            .add_systems(
+                Update,
+                projectile_terrain.run_if(resource_exists::<TileMap>),
+            );
     }
 }
 
 #[derive(Component)]
 pub struct Fireball;
 
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[reflect(Component)]
+/// Identifies the ability an entity (or a damage instance) originates from
+pub enum AbilityKind {
+    Fireball,
+    Mend,
+    HealthPotion,
+    Lifesteal,
+    Burn,
+    Regeneration,
+    Melee,
+    Arrow,
+}
+
+impl AbilityKind {
+    pub fn display_name(&self) -> String {
+        match self {
+            AbilityKind::Fireball => "Fireball".to_string(),
+            AbilityKind::Mend => "Mend".to_string(),
+            AbilityKind::HealthPotion => "Health potion".to_string(),
+            AbilityKind::Lifesteal => "Lifesteal".to_string(),
+            AbilityKind::Burn => "Burn".to_string(),
+            AbilityKind::Regeneration => "Regeneration".to_string(),
+            AbilityKind::Melee => "Attack".to_string(),
+            AbilityKind::Arrow => "Arrow".to_string(),
+        }
+    }
+    pub fn damage_type(&self) -> DamageType {
+        match self {
+            AbilityKind::Fireball | AbilityKind::Burn => DamageType::Fire,
+            _ => DamageType::Physical,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Projectile {
+    /// Speed in tiles per second
     pub speed: f32,
     pub damage: f32,
     pub moving: bool,
+    /// Entity that fired the projectile
+    pub owner: Option<Entity>,
+    /// Knockback applied on hit, in tiles per second
+    pub knockback: f32,
 }
 
 impl Default for Projectile {
@@ -53,30 +107,110 @@ impl Default for Projectile {
             speed: 7.5,
             damage: 35.0,
             moving: false,
+            owner: None,
+            knockback: 4.0,
         }
     }
 }
 
-fn projectile_collision(
+/// Spawns an arrow flying in `direction`, hitting entities hostile to `faction`
+pub fn spawn_arrow(
+    commands: &mut Commands,
+    projectile: Projectile,
+    origin: Vec3,
+    direction: Vec2,
+    damage: f32,
+    crit: CriticalHit,
+    faction: Faction,
+) {
+    let velocity = Velocity(direction * projectile.speed);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: ARROW_COLOR,
+                custom_size: Some(ARROW_SIZE),
+                ..default()
+            },
+            transform: Transform::from_translation(origin)
+                .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
+            ..default()
+        },
+        projectile,
+        velocity,
+        faction,
+        AbilityKind::Arrow,
+        projectile_collider(),
+        Damage::new(damage),
+        crit,
+        DespawnTimer(Timer::from_seconds(ARROW_LIFETIME, TimerMode::Once)),
+        Name::new("arrow"),
+    ));
+}
+
+/// Collider shared by every projectile
+pub fn projectile_collider() -> Collider {
+    Collider::circle(TILE_SIZE * 0.25).with_layers(PROJECTILE_LAYER, PLAYER_LAYER | ENEMY_LAYER)
+}
+
+/// Projectiles hit the first living entity hostile to their faction they collide with
+#[allow(clippy::too_many_arguments)]
+fn projectile_hits(
     mut commands: Commands,
-    mut q_projectiles: Query<(Entity, &Transform, &Damage, &CriticalHit), With<Projectile>>,
-    mut q_enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut collisions: EventReader<CollisionEvent>,
+    q_projectiles: Query<(
+        &Projectile,
+        &AbilityKind,
+        &Faction,
+        &Velocity,
+        &Damage,
+        &CriticalHit,
+    )>,
+    q_on_hit: Query<&OnHitEffect>,
+    q_targets: Query<&Faction, (With<Health>, Without<Dead>)>,
     mut ev_damage: EventWriter<DamageEvent>,
+    mut ev_effect: EventWriter<ApplyEffectEvent>,
+    relations: Res<FactionRelations>,
+) {
+    let mut spent = HashSet::new();
+    for (projectile_entity, target) in collisions.read().flat_map(CollisionEvent::pairs) {
+        if spent.contains(&projectile_entity) {
+            continue;
+        }
+        let (Ok((projectile, ability, faction, velocity, damage, crit)), Ok(target_faction)) =
+            (q_projectiles.get(projectile_entity), q_targets.get(target))
+        else {
+            continue;
+        };
+        if !relations.is_hostile(*faction, *target_faction) || projectile.owner == Some(target) {
+            continue;
+        }
+        spent.insert(projectile_entity);
+        commands.entity(projectile_entity).despawn_recursive();
+        ev_damage.send(DamageEvent {
+            damage: **damage,
+            crit_hit: *crit,
+            entity: target,
+            source: projectile.owner,
+            ability: *ability,
+            knockback: Some(velocity.normalize_or_zero() * projectile.knockback),
+        });
+        if let Ok(on_hit) = q_on_hit.get(projectile_entity) {
+            ev_effect.send(ApplyEffectEvent {
+                target,
+                effect: on_hit.0.clone(),
+            });
+        }
+    }
+}
+/// Projectiles break on the tiles they can't fly over
+fn projectile_terrain(
+    mut commands: Commands,
+    q_projectiles: Query<(Entity, &Transform), With<Projectile>>,
+    tilemap: Res<TileMap>,
 ) {
-    for (projectile_entity, projectile_transform, damage, crit) in q_projectiles.iter_mut() {
-        for (enemy_entity, enemy_transform) in q_enemies.iter_mut() {
-            let distance = enemy_transform
-                .translation
-                .distance(projectile_transform.translation);
-            if distance < (TILE_SIZE * 0.75) {
-                commands.entity(projectile_entity).despawn_recursive();
-                ev_damage.send(DamageEvent {
-                    damage: **damage,
-                    crit_hit: *crit,
-                    entity: enemy_entity,
-                });
-                break;
-            }
+    for (entity, transform) in q_projectiles.iter() {
+        if tilemap.blocks_projectiles_at(transform.translation.truncate()) {
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
@@ -115,35 +249,6 @@ fn load_abilities(
     });
 }
 
-fn projectile_mouvement(mut query: Query<(&Projectile, &Facing, &mut Transform)>, time: Res<Time>) {
-    let projectiles = query.iter_mut();
-    for (projectile, facing, mut transform) in projectiles {
-        let delta = match facing {
-            Facing::Up => Vec3::new(
-                0.0,
-                projectile.speed * TILE_SIZE * time.delta_seconds(),
-                0.0,
-            ),
-            Facing::Down => Vec3::new(
-                0.0,
-                -projectile.speed * TILE_SIZE * time.delta_seconds(),
-                0.0,
-            ),
-            Facing::Left => Vec3::new(
-                -projectile.speed * TILE_SIZE * time.delta_seconds(),
-                0.0,
-                0.0,
-            ),
-            Facing::Right => Vec3::new(
-                projectile.speed * TILE_SIZE * time.delta_seconds(),
-                0.0,
-                0.0,
-            ),
-        };
-        let target = transform.translation + delta;
-        transform.translation = target;
-    }
-}
 fn animate_fireball(
     mut sprites_query: Query<(&mut TextureAtlas, &mut FrameAnimation), With<Fireball>>,
     time: Res<Time>,
@@ -156,34 +261,3 @@ fn animate_fireball(
         }
     }
 }
-
-fn display_damage_numbers(
-    mut commands: Commands,
-    mut events: EventReader<DisplayDamageNumbersEvent>,
-) {
-    for event in events.read() {
-        let mut rng = rand::thread_rng();
-        let x = event.position.translation.x + rng.gen_range(-10.0..10.0);
-        let y = event.position.translation.y + rng.gen_range(-10.0..10.0);
-        let color = if event.is_crit {
-            Color::ORANGE
-        } else {
-            Color::WHITE
-        };
-        let font_size = if event.is_crit { 24.0 } else { 20.0 };
-        commands
-            .spawn(Text2dBundle {
-                text: Text::from_section(
-                    format!("{:.0}", event.damage),
-                    TextStyle {
-                        font: Handle::default(),
-                        font_size,
-                        color,
-                    },
-                ),
-                transform: Transform::from_xyz(x, y, 2.0),
-                ..Default::default()
-            })
-            .insert(DespawnTimer(Timer::from_seconds(0.5, TimerMode::Once)));
-    }
-}
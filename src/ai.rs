@@ -0,0 +1,363 @@
+use crate::abilities::{spawn_arrow, AbilityKind, Projectile};
+use crate::chunks::Dormant;
+use crate::damage::{CriticalHit, Damage, DamageEvent, HealEvent, HitStun, Invulnerable};
+use crate::enemy::{Enemy, EnemyAbilities, SPAWN_REACHED_DISTANCE};
+use crate::enemy_catalog::AiProfile;
+use crate::entities::{get_facing_direction, Dead, Facing, FrameAnimation, Health};
+use crate::faction::Faction;
+use crate::kinematics::{arrive, Velocity};
+use crate::pathfinding::PathFollower;
+use crate::threat::ThreatTable;
+use crate::tilemap::TileMap;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+/// Wandering speed, relative to the enemy speed
+const WANDER_SPEED: f32 = 0.5;
+/// Returning speed, relative to the enemy speed
+const RETURN_SPEED: f32 = 4.0; // TODO evaluate: Move back multiplier
+/// Knockback applied by melee attacks, in tiles per second
+const MELEE_KNOCKBACK: f32 = 2.0;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<EnemyAi>()
+            .register_type::<AiState>()
+            .add_systems(Update, (update_ai_state, run_ai).chain());
+    }
+}
+
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq)]
+pub enum AiState {
+    #[default]
+    Idle,
+    /// Walking to a random point around the spawn
+    Wander {
+        destination: Vec3,
+    },
+    Chase {
+        target: Entity,
+    },
+    Attack {
+        target: Entity,
+    },
+    /// Ranged enemies backing away from a target that came too close, still shooting
+    Kite {
+        target: Entity,
+    },
+    /// Running away from the target at low health
+    Flee {
+        target: Entity,
+    },
+    /// Going back to the spawn, immune to damage (evading)
+    Return,
+}
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+/// Per enemy AI state machine, transitions are evaluated every frame by `update_ai_state`
+pub struct EnemyAi {
+    pub state: AiState,
+    /// Time spent idle before wandering
+    pub idle_timer: Timer,
+    pub attack_cooldown: Timer,
+    /// Maximum distance from the spawn when wandering
+    pub wander_radius: f32,
+    pub attack_range: f32,
+    /// Fraction of max health under which the enemy flees, 0 to never flee
+    pub flee_health: f32,
+    /// Set for enemies attacking with projectiles instead of melee hits
+    pub ranged: Option<RangedAttack>,
+}
+
+#[derive(Component, Debug)]
+/// Enemy made invulnerable by `update_ai_state` while it returns to its spawn
+pub struct Evading;
+
+#[derive(Reflect, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RangedAttack {
+    /// Distance under which the enemy backs away from its target
+    pub keep_distance: f32,
+    /// Projectile speed in tiles per second
+    pub projectile_speed: f32,
+}
+
+impl Default for EnemyAi {
+    fn default() -> Self {
+        Self {
+            state: AiState::Idle,
+            idle_timer: Timer::from_seconds(3.0, TimerMode::Once),
+            attack_cooldown: Timer::from_seconds(1.5, TimerMode::Once),
+            wander_radius: 100.0,
+            attack_range: 40.0,
+            flee_health: 0.2,
+            ranged: None,
+        }
+    }
+}
+
+impl From<&AiProfile> for EnemyAi {
+    fn from(profile: &AiProfile) -> Self {
+        Self {
+            attack_cooldown: Timer::from_seconds(profile.attack_cooldown, TimerMode::Once),
+            wander_radius: profile.wander_radius,
+            attack_range: profile.attack_range,
+            flee_health: profile.flee_health,
+            ranged: profile.ranged,
+            ..default()
+        }
+    }
+}
+
+impl EnemyAi {
+    fn set_state(&mut self, state: AiState) {
+        if self.state == state {
+            return;
+        }
+        if state == AiState::Idle {
+            let idle_time = rand::thread_rng().gen_range(2.0..5.0);
+            self.idle_timer = Timer::from_seconds(idle_time, TimerMode::Once);
+        }
+        self.state = state;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_ai_state(
+    mut commands: Commands,
+    mut q_enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &Enemy,
+            &mut EnemyAi,
+            &ThreatTable,
+            &Health,
+            Has<Evading>,
+        ),
+        (Without<Dead>, Without<Dormant>),
+    >,
+    q_targets: Query<&Transform>,
+    mut heal_events: EventWriter<HealEvent>,
+    tilemap: Option<Res<TileMap>>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, transform, enemy, mut ai, threat, health, evading) in q_enemies.iter_mut() {
+        let position = transform.translation;
+        let distance_to_spawn = position.distance(enemy.spawn_coords);
+        let target = threat
+            .top()
+            .and_then(|target| q_targets.get(target).ok().map(|t| (target, t)));
+        let previous = ai.state;
+
+        let next = match (previous, target) {
+            (AiState::Return, _) if distance_to_spawn > SPAWN_REACHED_DISTANCE => AiState::Return,
+            _ if enemy.evading => AiState::Return,
+            (_, Some((target, target_transform))) => {
+                let distance = position.distance(target_transform.translation);
+                if health.current() <= health.max() * ai.flee_health {
+                    AiState::Flee { target }
+                } else if ai
+                    .ranged
+                    .is_some_and(|ranged| distance < ranged.keep_distance)
+                {
+                    AiState::Kite { target }
+                } else if distance <= ai.attack_range {
+                    AiState::Attack { target }
+                } else {
+                    AiState::Chase { target }
+                }
+            }
+            // Combat ended (target dead or gone), go back home
+            (
+                AiState::Chase { .. }
+                | AiState::Attack { .. }
+                | AiState::Kite { .. }
+                | AiState::Flee { .. },
+                None,
+            ) => AiState::Return,
+            (AiState::Return, None) => AiState::Idle,
+            (AiState::Idle, None) => {
+                if ai.idle_timer.tick(time.delta()).finished() {
+                    let offset = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                        * rng.gen_range(0.0..ai.wander_radius);
+                    let destination = enemy.spawn_coords + offset.extend(0.0);
+                    // Blocked destinations are picked again on the next frame
+                    if tilemap
+                        .as_ref()
+                        .is_some_and(|map| map.blocks_movement_at(destination.truncate()))
+                    {
+                        AiState::Idle
+                    } else {
+                        AiState::Wander { destination }
+                    }
+                } else {
+                    AiState::Idle
+                }
+            }
+            (AiState::Wander { destination }, None) => {
+                if position.distance(destination) <= SPAWN_REACHED_DISTANCE {
+                    AiState::Idle
+                } else {
+                    AiState::Wander { destination }
+                }
+            }
+        };
+
+        // Evading enemies are immune and reset to full health once home
+        if next == AiState::Return && previous != AiState::Return {
+            commands
+                .entity(entity)
+                .insert((Evading, Invulnerable::indefinite()));
+        } else if previous == AiState::Return && next != AiState::Return {
+            // Invulnerability granted by anything other than evading is left alone
+            if evading {
+                commands.entity(entity).remove::<(Evading, Invulnerable)>();
+            }
+            heal_events.send(HealEvent {
+                amount: health.max(),
+                entity,
+                source: Some(entity),
+                ability: AbilityKind::Regeneration,
+            });
+        }
+        ai.set_state(next);
+    }
+}
+
+/// Moves and attacks according to the current AI state
+#[allow(clippy::type_complexity)]
+pub fn run_ai(
+    mut commands: Commands,
+    mut q_enemies: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Velocity,
+            &mut Facing,
+            &mut Enemy,
+            &mut EnemyAi,
+            &mut PathFollower,
+            &mut FrameAnimation,
+            &Damage,
+            &CriticalHit,
+            &EnemyAbilities,
+            &Faction,
+            Has<HitStun>,
+        ),
+        (Without<Dead>, Without<Dormant>),
+    >,
+    q_targets: Query<&Transform>,
+    mut damage_events: EventWriter<DamageEvent>,
+    tilemap: Option<Res<TileMap>>,
+    time: Res<Time>,
+) {
+    for (
+        entity,
+        transform,
+        mut velocity,
+        mut facing,
+        mut enemy,
+        mut ai,
+        mut path,
+        mut animation,
+        damage,
+        crit,
+        abilities,
+        faction,
+        stunned,
+    ) in q_enemies.iter_mut()
+    {
+        // Stunned enemies stand still, only the knockback moves them
+        if stunned {
+            **velocity = Vec2::ZERO;
+            continue;
+        }
+        ai.attack_cooldown.tick(time.delta());
+        path.tick(time.delta());
+        let position = transform.translation;
+        let target_position = |target: Entity| q_targets.get(target).ok().map(|t| t.translation);
+        // Next point on the way to a destination, around the terrain
+        let mut walk_to = |destination: Vec3| match tilemap.as_deref() {
+            Some(map) => path
+                .next_waypoint(map, position.truncate(), destination.truncate())
+                .extend(destination.z),
+            None => destination,
+        };
+        let movement = match ai.state {
+            AiState::Idle => None,
+            AiState::Wander { destination } => Some(arrive(
+                position,
+                walk_to(destination),
+                enemy.speed * WANDER_SPEED,
+            )),
+            AiState::Chase { target } => target_position(target).map(|target| {
+                (walk_to(target) - position).truncate().normalize_or_zero() * enemy.speed
+            }),
+            AiState::Flee { target } => target_position(target)
+                .map(|target| (position - target).truncate().normalize_or_zero() * enemy.speed),
+            AiState::Return => Some(arrive(
+                position,
+                walk_to(enemy.spawn_coords),
+                enemy.speed * RETURN_SPEED,
+            )),
+            AiState::Attack { target } | AiState::Kite { target } => target_position(target)
+                .and_then(|target_position| {
+                    let direction = (target_position - position).truncate().normalize_or_zero();
+                    if ai.attack_cooldown.finished() {
+                        ai.attack_cooldown.reset();
+                        match ai.ranged {
+                            Some(ranged) => spawn_arrow(
+                                &mut commands,
+                                Projectile {
+                                    speed: ranged.projectile_speed,
+                                    owner: Some(entity),
+                                    ..default()
+                                },
+                                position,
+                                direction,
+                                **damage,
+                                *crit,
+                                *faction,
+                            ),
+                            None => {
+                                damage_events.send(DamageEvent {
+                                    damage: **damage,
+                                    crit_hit: *crit,
+                                    entity: target,
+                                    source: Some(entity),
+                                    ability: abilities.attack(),
+                                    knockback: Some(direction * MELEE_KNOCKBACK),
+                                });
+                            }
+                        }
+                    }
+                    if matches!(ai.state, AiState::Kite { .. }) {
+                        Some(-direction * enemy.speed)
+                    } else {
+                        *facing = get_facing_direction(direction.extend(0.0));
+                        None
+                    }
+                }),
+        };
+        **velocity = movement.unwrap_or_default();
+        match movement {
+            Some(movement) if movement != Vec2::ZERO => {
+                *facing = get_facing_direction(movement.extend(0.0));
+                enemy.moving = true;
+                animation.timer.unpause();
+            }
+            _ => {
+                if ai.state == AiState::Idle {
+                    *facing = Facing::Down;
+                }
+                enemy.moving = false;
+                animation.timer.pause();
+            }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::abilities::{spawn_arrow, Projectile};
+use crate::chunks::Dormant;
+use crate::damage::{CriticalHit, Damage};
+use crate::enemy::{set_nameplate, spawn_enemy, Enemy, EnemyNameUI, EnemySheets, EnemyType};
+use crate::enemy_catalog::{EnemyCatalog, EnemyCatalogHandle};
+use crate::entities::{get_facing_direction, Dead, Health};
+use crate::faction::Faction;
+use crate::threat::ThreatTable;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use rand::Rng;
+use serde::Deserialize;
+
+const BOSS_NAME_COLOR: Color = Color::rgb(1.0, 0.55, 0.1);
+const BOSS_BAR_WIDTH: f32 = 400.0;
+const BOSS_BAR_HEIGHT: f32 = 16.0;
+/// Summoned allies appear within this distance of the boss
+const SUMMON_RADIUS: f32 = TILE_SIZE * 1.5;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_boss_bar)
+            .add_systems(Update, set_boss_nameplate)
+            .add_systems(
+                Update,
+                (
+                    boss_phases,
+                    boss_patterns.run_if(resource_exists::<EnemySheets>),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, update_boss_bar);
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+/// Scripted encounter of a boss, phases are ordered by decreasing health
+pub struct BossProfile {
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPhase {
+    /// Fraction of max health under which the phase starts
+    pub health: f32,
+    /// Time between two special attacks, in seconds
+    pub interval: f32,
+    /// Special attacks, used in turn
+    pub patterns: Vec<BossPattern>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum BossPattern {
+    /// Arrows fired in every direction
+    Nova { projectiles: u32, speed: f32 },
+    /// Arrows fanned toward the target, `spread` being the total angle in degrees
+    Volley {
+        projectiles: u32,
+        spread: f32,
+        speed: f32,
+    },
+    /// Allies joining the fight around the boss
+    Summon { enemy_type: EnemyType, count: u32 },
+}
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    profile: BossProfile,
+    phase: usize,
+    /// Next pattern of the phase
+    pattern: usize,
+    timer: Timer,
+}
+
+impl Boss {
+    pub fn new(profile: BossProfile) -> Self {
+        let mut boss = Self {
+            profile,
+            phase: 0,
+            pattern: 0,
+            timer: Timer::default(),
+        };
+        boss.enter_phase(0);
+        boss
+    }
+    /// Starts `phase` from its first pattern
+    fn enter_phase(&mut self, phase: usize) {
+        let interval = self
+            .profile
+            .phases
+            .get(phase)
+            .map_or(1.0, |phase| phase.interval);
+        self.phase = phase;
+        self.pattern = 0;
+        self.timer = Timer::from_seconds(interval, TimerMode::Repeating);
+    }
+    fn current_phase(&self) -> Option<&BossPhase> {
+        self.profile.phases.get(self.phase)
+    }
+    /// Index of the current phase, starting at 1
+    pub fn phase_number(&self) -> usize {
+        self.phase + 1
+    }
+}
+
+#[derive(Component)]
+struct BossBar;
+#[derive(Component)]
+struct BossBarName;
+#[derive(Component)]
+struct BossBarFill;
+
+fn set_boss_nameplate(
+    q_bosses: Query<(&Enemy, &Children), Added<Boss>>,
+    mut q_names: Query<&mut Text, With<EnemyNameUI>>,
+) {
+    for (enemy, children) in q_bosses.iter() {
+        set_nameplate(children, &mut q_names, &enemy.display_name, BOSS_NAME_COLOR);
+    }
+}
+
+/// Moves to the last phase whose health threshold was crossed, a single hit can skip phases.
+/// The encounter starts over when the boss is healed back to full after evading.
+/// Bosses are checked again when they wake up, for the damage taken while dormant
+#[allow(clippy::type_complexity)]
+fn boss_phases(
+    mut q_bosses: Query<(Entity, &mut Boss, Ref<Health>), (Without<Dead>, Without<Dormant>)>,
+    mut woken: RemovedComponents<Dormant>,
+) {
+    let woken = woken.read().collect::<HashSet<_>>();
+    for (entity, mut boss, health) in q_bosses.iter_mut() {
+        if !health.is_changed() && !woken.contains(&entity) {
+            continue;
+        }
+        let ratio = health.current() / health.max();
+        if ratio >= 1.0 {
+            if boss.phase != 0 {
+                boss.enter_phase(0);
+            }
+            continue;
+        }
+        let mut phase = boss.phase;
+        while boss
+            .profile
+            .phases
+            .get(phase + 1)
+            .is_some_and(|next| ratio <= next.health)
+        {
+            phase += 1;
+        }
+        if phase != boss.phase {
+            boss.enter_phase(phase);
+        }
+    }
+}
+
+/// Uses the special attacks of the current phase, in turn, while in combat
+#[allow(clippy::type_complexity)]
+fn boss_patterns(
+    mut commands: Commands,
+    mut q_bosses: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Boss,
+            &ThreatTable,
+            &Damage,
+            &CriticalHit,
+            &Faction,
+        ),
+        (Without<Dead>, Without<Dormant>),
+    >,
+    q_targets: Query<&Transform>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, transform, mut boss, threat, damage, crit, faction) in q_bosses.iter_mut() {
+        let Some((target, target_transform)) = threat
+            .top()
+            .and_then(|target| q_targets.get(target).ok().map(|t| (target, t)))
+        else {
+            continue;
+        };
+        if !boss.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let Some(phase) = boss.current_phase() else {
+            continue;
+        };
+        let Some(&pattern) = phase
+            .patterns
+            .get(boss.pattern % phase.patterns.len().max(1))
+        else {
+            continue;
+        };
+        boss.pattern += 1;
+
+        let position = transform.translation;
+        let aim = (target_transform.translation - position)
+            .truncate()
+            .normalize_or_zero();
+        let mut fire = |direction: Vec2, speed: f32| {
+            spawn_arrow(
+                &mut commands,
+                Projectile {
+                    speed,
+                    owner: Some(entity),
+                    ..default()
+                },
+                position,
+                direction,
+                **damage,
+                *crit,
+                *faction,
+            );
+        };
+        match pattern {
+            BossPattern::Nova { projectiles, speed } => {
+                for i in 0..projectiles {
+                    let angle = std::f32::consts::TAU * i as f32 / projectiles as f32;
+                    fire(Vec2::from_angle(angle), speed);
+                }
+            }
+            BossPattern::Volley {
+                projectiles,
+                spread,
+                speed,
+            } => {
+                let spread = spread.to_radians();
+                for i in 0..projectiles {
+                    let offset = if projectiles > 1 {
+                        spread * (i as f32 / (projectiles - 1) as f32 - 0.5)
+                    } else {
+                        0.0
+                    };
+                    fire(Vec2::from_angle(offset).rotate(aim), speed);
+                }
+            }
+            BossPattern::Summon { enemy_type, count } => {
+                let (Some(definition), Some(sheet)) = (
+                    catalogs
+                        .get(&**catalog)
+                        .and_then(|catalog| catalog.get(enemy_type)),
+                    sheets.get(&enemy_type),
+                ) else {
+                    continue;
+                };
+                for _ in 0..count {
+                    let offset =
+                        Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)) * SUMMON_RADIUS;
+                    let summoned = spawn_enemy(
+                        &mut commands,
+                        enemy_type,
+                        definition,
+                        sheet,
+                        position + offset.extend(0.0),
+                        get_facing_direction(aim.extend(0.0)),
+                        definition.stats.level,
+                    );
+                    // Summoned allies join the fight right away
+                    let mut threat = ThreatTable::default();
+                    threat.add(target, 1.0);
+                    commands.entity(summoned).insert(threat);
+                }
+            }
+        }
+    }
+}
+
+fn setup_boss_bar(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.0),
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+            BossBar,
+            Name::new("boss bar"),
+        ))
+        .with_children(|p| {
+            p.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: BOSS_NAME_COLOR,
+                        font_size: 20.0,
+                        ..default()
+                    },
+                ),
+                BossBarName,
+            ));
+            p.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(BOSS_BAR_WIDTH),
+                    height: Val::Px(BOSS_BAR_HEIGHT),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                ..default()
+            })
+            .with_children(|p| {
+                p.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::RED.into(),
+                        ..default()
+                    },
+                    BossBarFill,
+                ));
+            });
+        });
+}
+
+/// Shows the health of the boss the player is fighting at the top of the screen
+fn update_boss_bar(
+    q_bosses: Query<(&Enemy, &Boss, &Health, &ThreatTable), Without<Dead>>,
+    mut q_bar: Query<&mut Style, (With<BossBar>, Without<BossBarFill>)>,
+    mut q_fill: Query<&mut Style, (With<BossBarFill>, Without<BossBar>)>,
+    mut q_name: Query<&mut Text, With<BossBarName>>,
+) {
+    let engaged = q_bosses.iter().find(|(_, _, _, threat)| threat.in_combat());
+    let Ok(mut bar) = q_bar.get_single_mut() else {
+        return;
+    };
+    let Some((enemy, boss, health, _)) = engaged else {
+        bar.display = Display::None;
+        return;
+    };
+    bar.display = Display::Flex;
+    if let Ok(mut fill) = q_fill.get_single_mut() {
+        fill.width = Val::Percent(health.current() / health.max() * 100.0);
+    }
+    if let Ok(mut name) = q_name.get_single_mut() {
+        name.sections[0].value = format!("{} - Phase {}", enemy.display_name, boss.phase_number());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_boss(app: &mut App) -> Entity {
+        let phase = |health| BossPhase {
+            health,
+            interval: 1.0,
+            patterns: vec![],
+        };
+        let profile = BossProfile {
+            phases: vec![phase(1.0), phase(0.6), phase(0.3)],
+        };
+        app.world
+            .spawn((Boss::new(profile), Health::new(100.0)))
+            .id()
+    }
+
+    fn set_health(app: &mut App, boss: Entity, current: f32) {
+        let mut health = app.world.get_mut::<Health>(boss).unwrap();
+        *health = Health::new(100.0);
+        health.update(100.0 - current);
+        app.update();
+    }
+
+    fn phase(app: &App, boss: Entity) -> usize {
+        app.world.get::<Boss>(boss).unwrap().phase
+    }
+
+    #[test]
+    fn big_hits_skip_phases() {
+        let mut app = App::new();
+        app.add_systems(Update, boss_phases);
+        let boss = spawn_boss(&mut app);
+        set_health(&mut app, boss, 25.0);
+        assert_eq!(phase(&app, boss), 2);
+    }
+
+    #[test]
+    fn full_heal_restarts_the_encounter() {
+        let mut app = App::new();
+        app.add_systems(Update, boss_phases);
+        let boss = spawn_boss(&mut app);
+        set_health(&mut app, boss, 50.0);
+        assert_eq!(phase(&app, boss), 1);
+        set_health(&mut app, boss, 100.0);
+        assert_eq!(phase(&app, boss), 0);
+    }
+
+    #[test]
+    fn damage_taken_while_dormant_counts_on_wake_up() {
+        let mut app = App::new();
+        app.add_systems(Update, boss_phases);
+        let boss = spawn_boss(&mut app);
+        app.world.entity_mut(boss).insert(Dormant);
+        set_health(&mut app, boss, 50.0);
+        assert_eq!(phase(&app, boss), 0);
+        app.world.entity_mut(boss).remove::<Dormant>();
+        app.update();
+        assert_eq!(phase(&app, boss), 1);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::boss::Boss;
+use crate::elite::{apply_elite_affixes, Elite, EliteAffix};
+use crate::enemy::{spawn_enemy, Enemy, EnemySheets, EnemyType};
+use crate::enemy_catalog::{catalog_loaded, EnemyCatalog, EnemyCatalogHandle};
+use crate::entities::{Dead, Health, HealthUpdateEvent};
+use crate::kinematics::Velocity;
+use crate::player::Player;
+use crate::respawn::SpawnSlotId;
+use crate::spawner::{random_facing, ArenaEnemy};
+use crate::tilemap::TileMap;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Width of a chunk, in tiles
+const CHUNK_TILES: i32 = 8;
+const CHUNK_SIZE: f32 = TILE_SIZE * CHUNK_TILES as f32;
+/// Enemies up to this many chunks away from the player's chunk are simulated
+const ACTIVE_RADIUS: i32 = 2;
+/// Chunks up to this many chunks away stay in the world, their enemies frozen when not active.
+/// Enemies further away are stored until their chunk loads again
+const LOADED_RADIUS: i32 = 3;
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkManager>()
+            .add_systems(
+                Update,
+                (
+                    update_chunks,
+                    update_enemy_activity,
+                    restore_enemies
+                        .run_if(catalog_loaded.and_then(resource_exists::<EnemySheets>))
+                        .before(apply_elite_affixes),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, restore_health.after(apply_elite_affixes));
+    }
+}
+
+#[derive(Component)]
+/// Enemy in a loaded chunk too far from the player to be simulated
+pub struct Dormant;
+
+#[derive(Component)]
+/// Fraction of its max health a restored enemy comes back with, applied once its affixes are
+pub struct RestoredHealth(f32);
+
+#[derive(Debug, Clone, PartialEq)]
+/// Enemy removed from the world with its chunk
+pub struct StoredEnemy {
+    pub enemy_type: EnemyType,
+    pub level: u32,
+    pub position: Vec3,
+    pub spawn_coords: Vec3,
+    /// Fraction of its max health
+    pub health: f32,
+    pub affixes: Vec<EliteAffix>,
+    pub slot: Option<SpawnSlotId>,
+}
+
+#[derive(Resource, Debug, Default)]
+/// Chunks around the player, and the enemies of the chunks out of the world
+pub struct ChunkManager {
+    /// Chunk the player is in
+    center: Option<IVec2>,
+    stored: HashMap<IVec2, Vec<StoredEnemy>>,
+}
+
+impl ChunkManager {
+    pub fn chunk_of(position: Vec2) -> IVec2 {
+        (position / CHUNK_SIZE).floor().as_ivec2()
+    }
+    /// Number of chunks between `chunk` and the player's chunk, diagonals counting as one
+    fn distance(&self, chunk: IVec2) -> Option<i32> {
+        self.center
+            .map(|center| (chunk - center).abs().max_element())
+    }
+    pub fn is_active(&self, chunk: IVec2) -> bool {
+        self.distance(chunk)
+            .is_some_and(|distance| distance <= ACTIVE_RADIUS)
+    }
+    pub fn is_loaded(&self, chunk: IVec2) -> bool {
+        self.distance(chunk)
+            .is_some_and(|distance| distance <= LOADED_RADIUS)
+    }
+    /// Every chunk currently in the world
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.center.into_iter().flat_map(|center| {
+            (-LOADED_RADIUS..=LOADED_RADIUS).flat_map(move |y| {
+                (-LOADED_RADIUS..=LOADED_RADIUS).map(move |x| center + IVec2::new(x, y))
+            })
+        })
+    }
+    /// Tiles covered by `chunk`, from its bottom left tile (inclusive) to its top right (exclusive)
+    pub fn chunk_tiles(map: &TileMap, chunk: IVec2) -> (IVec2, IVec2) {
+        let min = map.tile_at(chunk.as_vec2() * CHUNK_SIZE + TILE_SIZE / 2.0);
+        (min, min + CHUNK_TILES)
+    }
+}
+
+/// Follows the player across chunks. Stored enemies are dropped with the map they were on
+pub fn update_chunks(
+    mut manager: ResMut<ChunkManager>,
+    q_player: Query<&Transform, With<Player>>,
+    tilemap: Option<Res<TileMap>>,
+) {
+    if tilemap.is_some_and(|map| map.is_changed()) {
+        manager.stored.clear();
+    }
+    let Ok(transform) = q_player.get_single() else {
+        return;
+    };
+    let center = ChunkManager::chunk_of(transform.translation.truncate());
+    if manager.center != Some(center) {
+        manager.center = Some(center);
+    }
+}
+
+/// Freezes enemies outside of the active chunks and stores the ones outside of the loaded chunks.
+/// Bosses and arena enemies are only frozen, their fight is not persisted
+#[allow(clippy::type_complexity)]
+fn update_enemy_activity(
+    mut commands: Commands,
+    mut manager: ResMut<ChunkManager>,
+    mut q_enemies: Query<(
+        Entity,
+        &Transform,
+        &Enemy,
+        &EnemyType,
+        &Health,
+        &mut Velocity,
+        Option<&Elite>,
+        Option<&SpawnSlotId>,
+        Has<Dormant>,
+        Has<Dead>,
+    )>,
+    q_kept: Query<(), Or<(With<Boss>, With<ArenaEnemy>)>>,
+) {
+    if manager.center.is_none() {
+        return;
+    }
+    for (entity, transform, enemy, enemy_type, health, mut velocity, elite, slot, dormant, dead) in
+        q_enemies.iter_mut()
+    {
+        let chunk = ChunkManager::chunk_of(transform.translation.truncate());
+        if !manager.is_loaded(chunk) && !q_kept.contains(entity) {
+            // Corpses are not worth keeping
+            if !dead {
+                manager.stored.entry(chunk).or_default().push(StoredEnemy {
+                    enemy_type: *enemy_type,
+                    level: enemy.level,
+                    position: transform.translation,
+                    spawn_coords: enemy.spawn_coords,
+                    health: health.current() / health.max(),
+                    affixes: elite.map(|elite| elite.affixes.clone()).unwrap_or_default(),
+                    slot: slot.copied(),
+                });
+            }
+            commands.entity(entity).despawn_recursive();
+        } else if !manager.is_active(chunk) {
+            if !dormant && !dead {
+                velocity.0 = Vec2::ZERO;
+                commands.entity(entity).insert(Dormant);
+            }
+        } else if dormant {
+            commands.entity(entity).remove::<Dormant>();
+        }
+    }
+}
+
+/// Brings back the stored enemies of the chunks that loaded again
+fn restore_enemies(
+    mut commands: Commands,
+    mut manager: ResMut<ChunkManager>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+) {
+    let Some(catalog) = catalogs.get(&**catalog) else {
+        return;
+    };
+    let loaded = manager
+        .stored
+        .keys()
+        .copied()
+        .filter(|chunk| manager.is_loaded(*chunk))
+        .collect::<Vec<_>>();
+    let mut rng = rand::thread_rng();
+    for chunk in loaded {
+        for stored in manager.stored.remove(&chunk).unwrap_or_default() {
+            let (Some(definition), Some(sheet)) = (
+                catalog.get(stored.enemy_type),
+                sheets.get(&stored.enemy_type),
+            ) else {
+                continue;
+            };
+            let entity = spawn_enemy(
+                &mut commands,
+                stored.enemy_type,
+                definition,
+                sheet,
+                stored.spawn_coords,
+                random_facing(&mut rng),
+                stored.level,
+            );
+            let mut enemy = commands.entity(entity);
+            enemy.insert((
+                Transform::from_translation(stored.position),
+                RestoredHealth(stored.health),
+            ));
+            if let Some(slot) = stored.slot {
+                enemy.insert(slot);
+            }
+            if !stored.affixes.is_empty() {
+                enemy.insert(Elite {
+                    affixes: stored.affixes,
+                });
+            }
+        }
+    }
+}
+
+fn restore_health(
+    mut commands: Commands,
+    mut q_restored: Query<(Entity, &mut Health, &RestoredHealth)>,
+    mut health_events: EventWriter<HealthUpdateEvent>,
+) {
+    for (entity, mut health, restored) in q_restored.iter_mut() {
+        let max = health.max();
+        health.update(max * (1.0 - restored.0));
+        health_events.send(HealthUpdateEvent {
+            entity,
+            total_health: max,
+            new_health: health.current(),
+        });
+        commands.entity(entity).remove::<RestoredHealth>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_load_around_the_player() {
+        let manager = ChunkManager {
+            center: Some(ChunkManager::chunk_of(Vec2::new(-1.0, CHUNK_SIZE * 1.5))),
+            ..default()
+        };
+        assert_eq!(manager.center, Some(IVec2::new(-1, 1)));
+        assert!(manager.is_active(IVec2::new(-1 + ACTIVE_RADIUS, 1 - ACTIVE_RADIUS)));
+        assert!(!manager.is_active(IVec2::new(-1 + LOADED_RADIUS, 1)));
+        assert!(manager.is_loaded(IVec2::new(-1 + LOADED_RADIUS, 1)));
+        assert!(!manager.is_loaded(IVec2::new(-1, 2 + LOADED_RADIUS)));
+        let side = (LOADED_RADIUS * 2 + 1) as usize;
+        assert_eq!(manager.loaded_chunks().count(), side * side);
+    }
+
+    #[test]
+    fn nothing_loads_without_a_player() {
+        let manager = ChunkManager::default();
+        assert!(!manager.is_loaded(IVec2::ZERO));
+        assert_eq!(manager.loaded_chunks().count(), 0);
+    }
+}
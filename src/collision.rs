@@ -0,0 +1,258 @@
+use crate::entities::Dead;
+use crate::kinematics::integrate_velocity;
+use crate::spatial_grid::SpatialGrid;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+
+pub const PLAYER_LAYER: u32 = 1 << 0;
+pub const ENEMY_LAYER: u32 = 1 << 1;
+pub const PROJECTILE_LAYER: u32 = 1 << 2;
+
+/// Largest distance from an entity's position to the edge of its collider, in pixels.
+/// Bigger colliders can be missed by the broad phase
+const MAX_COLLIDER_REACH: f32 = TILE_SIZE * 1.5;
+const DEBUG_TOGGLE_KEY: KeyCode = KeyCode::F3;
+const DEBUG_COLOR: Color = Color::LIME_GREEN;
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Collider>()
+            .init_resource::<ColliderDebug>()
+            .add_event::<CollisionEvent>()
+            .add_systems(Update, detect_collisions.after(integrate_velocity))
+            .add_systems(Update, toggle_collider_debug)
+            .add_systems(
+                Update,
+                draw_colliders.run_if(|debug: Res<ColliderDebug>| debug.0),
+            );
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Circle {
+        radius: f32,
+    },
+    Aabb {
+        half_size: Vec2,
+    },
+    /// Vertical capsule, `half_height` is measured between the centers of the two caps
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+}
+
+impl ColliderShape {
+    /// Half size of the axis aligned box at the core of the shape, and the radius it is grown by.
+    /// A circle is a grown point and a capsule a grown vertical segment
+    fn core(&self) -> (Vec2, f32) {
+        match *self {
+            ColliderShape::Circle { radius } => (Vec2::ZERO, radius),
+            ColliderShape::Aabb { half_size } => (half_size, 0.0),
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => (Vec2::new(0.0, half_height), radius),
+        }
+    }
+    fn bounding_radius(&self) -> f32 {
+        let (half_size, radius) = self.core();
+        half_size.length() + radius
+    }
+}
+
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// Position of the shape relative to the entity, in pixels
+    pub offset: Vec2,
+    /// Layers the collider belongs to
+    pub layers: u32,
+    /// Layers the collider detects
+    pub mask: u32,
+}
+
+impl Collider {
+    pub fn circle(radius: f32) -> Self {
+        Self::new(ColliderShape::Circle { radius })
+    }
+    pub fn aabb(half_size: Vec2) -> Self {
+        Self::new(ColliderShape::Aabb { half_size })
+    }
+    pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self::new(ColliderShape::Capsule {
+            half_height,
+            radius,
+        })
+    }
+    fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            offset: Vec2::ZERO,
+            layers: 0,
+            mask: 0,
+        }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn with_layers(mut self, layers: u32, mask: u32) -> Self {
+        self.layers = layers;
+        self.mask = mask;
+        self
+    }
+    /// Half size of the box containing the shape
+    pub fn half_extents(&self) -> Vec2 {
+        let (half_size, radius) = self.shape.core();
+        half_size + radius
+    }
+    /// Whether this collider reports overlaps with `other`
+    pub fn detects(&self, other: &Collider) -> bool {
+        self.mask & other.layers != 0
+    }
+    /// Whether the two colliders overlap, placed on entities at the given positions
+    pub fn overlaps(&self, position: Vec2, other: &Collider, other_position: Vec2) -> bool {
+        let (half_size, radius) = self.shape.core();
+        let (other_half_size, other_radius) = other.shape.core();
+        let offset = (position + self.offset - other_position - other.offset).abs();
+        let gap = (offset - half_size - other_half_size).max(Vec2::ZERO);
+        gap.length_squared() <= (radius + other_radius).powi(2)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Two overlapping colliders, sent once per pair every frame they overlap
+pub struct CollisionEvent {
+    pub entity: Entity,
+    pub other: Entity,
+}
+
+impl CollisionEvent {
+    /// The pair in both orders, to match it against two queries
+    pub fn pairs(&self) -> [(Entity, Entity); 2] {
+        [(self.entity, self.other), (self.other, self.entity)]
+    }
+}
+
+#[derive(Resource, Default)]
+/// Draws every collider when enabled
+pub struct ColliderDebug(pub bool);
+
+/// Narrow phase over the neighbours found in the spatial grid
+pub fn detect_collisions(
+    q_colliders: Query<(Entity, &Transform, &Collider), Without<Dead>>,
+    grid: Res<SpatialGrid>,
+    mut events: EventWriter<CollisionEvent>,
+) {
+    for (entity, transform, collider) in q_colliders.iter() {
+        if collider.mask == 0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let reach = collider.offset.length() + collider.shape.bounding_radius();
+        for (other, _) in grid.query_radius(position, reach + MAX_COLLIDER_REACH) {
+            if other == entity {
+                continue;
+            }
+            let Ok((_, other_transform, other_collider)) = q_colliders.get(other) else {
+                continue;
+            };
+            if !collider.detects(other_collider) {
+                continue;
+            }
+            // When both detect each other, the pair is only reported from one side
+            if other_collider.detects(collider) && other < entity {
+                continue;
+            }
+            if collider.overlaps(
+                position,
+                other_collider,
+                other_transform.translation.truncate(),
+            ) {
+                events.send(CollisionEvent { entity, other });
+            }
+        }
+    }
+}
+
+fn toggle_collider_debug(keyboard: Res<ButtonInput<KeyCode>>, mut debug: ResMut<ColliderDebug>) {
+    if keyboard.just_pressed(DEBUG_TOGGLE_KEY) {
+        debug.0 = !debug.0;
+    }
+}
+
+fn draw_colliders(mut gizmos: Gizmos, q_colliders: Query<(&Transform, &Collider)>) {
+    for (transform, collider) in q_colliders.iter() {
+        let center = transform.translation.truncate() + collider.offset;
+        match collider.shape {
+            ColliderShape::Circle { radius } => {
+                gizmos.circle_2d(center, radius, DEBUG_COLOR);
+            }
+            ColliderShape::Aabb { half_size } => {
+                gizmos.rect_2d(center, 0.0, half_size * 2.0, DEBUG_COLOR);
+            }
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => {
+                let (top, bottom) = (Vec2::Y * half_height, Vec2::NEG_Y * half_height);
+                gizmos.circle_2d(center + top, radius, DEBUG_COLOR);
+                gizmos.circle_2d(center + bottom, radius, DEBUG_COLOR);
+                for side in [Vec2::X * radius, Vec2::NEG_X * radius] {
+                    gizmos.line_2d(center + top + side, center + bottom + side, DEBUG_COLOR);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circles_overlap_within_their_radii() {
+        let a = Collider::circle(10.0);
+        let b = Collider::circle(5.0);
+        assert!(a.overlaps(Vec2::ZERO, &b, Vec2::new(14.0, 0.0)));
+        assert!(!a.overlaps(Vec2::ZERO, &b, Vec2::new(16.0, 0.0)));
+    }
+
+    #[test]
+    fn circle_misses_box_corner() {
+        let aabb = Collider::aabb(Vec2::splat(10.0));
+        let circle = Collider::circle(5.0);
+        // 4 pixels away from each side of the corner, but 5.66 pixels from the corner itself
+        assert!(!aabb.overlaps(Vec2::ZERO, &circle, Vec2::splat(14.0)));
+        assert!(aabb.overlaps(Vec2::ZERO, &circle, Vec2::new(14.0, 0.0)));
+    }
+
+    #[test]
+    fn capsule_reaches_along_its_height() {
+        let capsule = Collider::capsule(20.0, 5.0);
+        let point = Collider::circle(1.0);
+        assert!(capsule.overlaps(Vec2::ZERO, &point, Vec2::new(0.0, 25.0)));
+        assert!(!capsule.overlaps(Vec2::ZERO, &point, Vec2::new(25.0, 0.0)));
+    }
+
+    #[test]
+    fn offsets_move_the_shape() {
+        let a = Collider::circle(5.0).with_offset(Vec2::new(0.0, 20.0));
+        let b = Collider::circle(5.0);
+        assert!(a.overlaps(Vec2::ZERO, &b, Vec2::new(0.0, 20.0)));
+        assert!(!a.overlaps(Vec2::ZERO, &b, Vec2::ZERO));
+    }
+
+    #[test]
+    fn masks_select_detected_layers() {
+        let projectile = Collider::circle(5.0).with_layers(PROJECTILE_LAYER, ENEMY_LAYER);
+        let enemy = Collider::circle(5.0).with_layers(ENEMY_LAYER, 0);
+        assert!(projectile.detects(&enemy));
+        assert!(!enemy.detects(&projectile));
+    }
+}
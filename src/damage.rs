@@ -1,18 +1,73 @@
-use crate::enemy::EnemyDefeatedEvent;
-use crate::entities::{Health, HealthUpdateEvent};
+use crate::abilities::AbilityKind;
+use crate::enemy::{Enemy, EnemyDefeatedEvent};
+use crate::entities::{Dead, Health, HealthUpdateEvent, Tint};
 use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+/// Exponential decay rate of the knockback velocity, per second
+const KNOCKBACK_DECAY: f32 = 12.0;
+/// Knockback velocity (tiles per second) under which the knockback stops
+const KNOCKBACK_MIN_SPEED: f32 = 0.1;
+/// Duration of the hit-stun applied to damaged entities, in seconds
+const HIT_STUN_DURATION: f32 = 0.2;
+/// Duration of the hit-flash tint applied to damaged entities, in seconds
+const HIT_FLASH_DURATION: f32 = 0.1;
+const HIT_FLASH_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+/// Time between two visibility toggles of a blinking invulnerable entity, in seconds
+const BLINK_INTERVAL: f32 = 0.1;
 
 #[derive(Event)]
 pub struct DamageEvent {
     pub damage: f32,
     pub crit_hit: CriticalHit,
     pub entity: Entity,
+    /// Entity responsible for the damage, if any
+    pub source: Option<Entity>,
+    pub ability: AbilityKind,
+    /// Impulse applied to the damaged entity, in tiles per second
+    pub knockback: Option<Vec2>,
+}
+#[derive(Event)]
+/// Sent once a damage event has been resolved, with the final (post crit) damage
+pub struct DamageDealtEvent {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub ability: AbilityKind,
+    pub damage: f32,
+    pub is_crit: bool,
+}
+#[derive(Event)]
+pub struct HealEvent {
+    pub amount: f32,
+    pub entity: Entity,
+    /// Entity responsible for the heal, if any
+    pub source: Option<Entity>,
+    pub ability: AbilityKind,
+}
+#[derive(Event)]
+/// Sent once a heal event has been resolved, `amount` excludes the overheal
+pub struct HealDealtEvent {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub ability: AbilityKind,
+    pub amount: f32,
+    pub overheal: f32,
 }
 #[derive(Event)]
 pub struct DisplayDamageNumbersEvent {
     pub damage: f32,
     pub position: Transform,
     pub is_crit: bool,
+    pub is_heal: bool,
+    /// Entity that was damaged or healed
+    pub entity: Entity,
+    pub ability: AbilityKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum DamageType {
+    Physical,
+    Fire,
 }
 
 #[derive(Component, Deref, DerefMut)]
@@ -37,27 +92,105 @@ impl CriticalHit {
         Self { chance, multiplier }
     }
 }
+
+#[derive(Component, Deref, DerefMut)]
+/// Fraction of the damage dealt by an entity that is returned to it as healing
+pub struct Lifesteal(f32);
+
+impl Lifesteal {
+    pub fn new(ratio: f32) -> Self {
+        Self(ratio)
+    }
+}
+
+#[derive(Component)]
+/// Makes an entity ignore all incoming damage, either for a duration or until removed
+pub struct Invulnerable(Option<Timer>);
+
+impl Invulnerable {
+    pub fn new(duration: f32) -> Self {
+        Self(Some(Timer::from_seconds(duration, TimerMode::Once)))
+    }
+    /// Invulnerability that lasts until the component is removed (e.g. cutscenes)
+    pub fn indefinite() -> Self {
+        Self(None)
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+/// Grants an entity invulnerability for the given duration (in seconds) after each hit
+pub struct InvulnerabilityFrames(f32);
+
+impl InvulnerabilityFrames {
+    pub fn new(duration: f32) -> Self {
+        Self(duration)
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+/// Velocity pushing an entity after a hit, in tiles per second
+pub struct Knockback(Vec2);
+
+#[derive(Component, Deref, DerefMut)]
+/// Prevents an entity from acting for a short time after a hit
+pub struct HitStun(Timer);
+
+impl Default for HitStun {
+    fn default() -> Self {
+        Self(Timer::from_seconds(HIT_STUN_DURATION, TimerMode::Once))
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+/// Tints the sprite of an entity for a short time after a hit
+pub struct HitFlash(Timer);
+
+impl Default for HitFlash {
+    fn default() -> Self {
+        Self(Timer::from_seconds(HIT_FLASH_DURATION, TimerMode::Once))
+    }
+}
 pub struct DamagePlugin;
 
 impl Plugin for DamagePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<DamageEvent>()
+            .add_event::<DamageDealtEvent>()
+            .add_event::<HealEvent>()
+            .add_event::<HealDealtEvent>()
             .add_event::<DisplayDamageNumbersEvent>()
             .add_event::<HealthUpdateEvent>()
-            .add_systems(Update, apply_damage_system);
+            .add_systems(
+                Update,
+                (apply_damage_system, apply_heal_system, detect_deaths).chain(),
+            )
+            .add_systems(Update, apply_knockback)
+            .add_systems(Update, tick_hit_stun)
+            .add_systems(Update, hit_flash)
+            .add_systems(Update, tick_invulnerability);
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn apply_damage_system(
     mut commands: Commands,
     mut damage_events: EventReader<DamageEvent>,
+    mut damage_dealt_events: EventWriter<DamageDealtEvent>,
     mut display_damage_events: EventWriter<DisplayDamageNumbersEvent>,
     mut health_update_events: EventWriter<HealthUpdateEvent>,
-    mut enemy_events: EventWriter<EnemyDefeatedEvent>,
-    mut q_health: Query<(Entity, &Transform, &mut Health)>,
+    mut heal_events: EventWriter<HealEvent>,
+    mut q_health: Query<(Entity, &Transform, &mut Health, Has<Invulnerable>), Without<Dead>>,
+    q_i_frames: Query<&InvulnerabilityFrames>,
+    q_lifesteal: Query<&Lifesteal>,
 ) {
+    // Entities that became invulnerable during this run, the component is only inserted later
+    let mut invulnerable = HashSet::new();
     for e in damage_events.read() {
-        if let Ok((entity, transform, mut health)) = q_health.get_mut(e.entity) {
+        if let Ok((entity, transform, mut health, is_invulnerable)) = q_health.get_mut(e.entity) {
+            // Health is already at 0 when a previous hit of this frame was lethal
+            if is_invulnerable || invulnerable.contains(&entity) || health.current() <= 0.0 {
+                continue;
+            }
             let mut final_damage = e.damage;
             if e.crit_hit.chance > 0.0 {
                 let random = rand::random::<f32>();
@@ -65,21 +198,325 @@ fn apply_damage_system(
                     final_damage *= e.crit_hit.multiplier;
                 }
             }
+            let is_crit = final_damage != e.damage;
+            let health_before = health.current();
             health.update(final_damage);
+            // Overkill is not dealt, it neither heals nor counts in meters and threat
+            let dealt = health_before - health.current();
+            damage_dealt_events.send(DamageDealtEvent {
+                source: e.source,
+                target: entity,
+                ability: e.ability,
+                damage: dealt,
+                is_crit,
+            });
             display_damage_events.send(DisplayDamageNumbersEvent {
                 damage: final_damage,
                 position: *transform,
-                is_crit: final_damage != e.damage,
+                is_crit,
+                is_heal: false,
+                entity,
+                ability: e.ability,
             });
             health_update_events.send(HealthUpdateEvent {
                 entity,
                 total_health: health.max(),
                 new_health: health.current(),
             });
+            if let Some(source) = e.source {
+                if let Ok(lifesteal) = q_lifesteal.get(source) {
+                    heal_events.send(HealEvent {
+                        amount: dealt * **lifesteal,
+                        entity: source,
+                        source: Some(source),
+                        ability: AbilityKind::Lifesteal,
+                    });
+                }
+            }
+            commands.entity(entity).insert(HitFlash::default());
+            // Only impacts stun, damage over time ticks don't
+            if let Some(knockback) = e.knockback {
+                commands
+                    .entity(entity)
+                    .insert((Knockback(knockback), HitStun::default()));
+            }
+            if health.current() > 0.0 {
+                if let Ok(i_frames) = q_i_frames.get(entity) {
+                    invulnerable.insert(entity);
+                    commands
+                        .entity(entity)
+                        .insert(Invulnerable::new(**i_frames));
+                }
+            }
+        }
+    }
+}
+
+fn apply_heal_system(
+    mut heal_events: EventReader<HealEvent>,
+    mut heal_dealt_events: EventWriter<HealDealtEvent>,
+    mut display_damage_events: EventWriter<DisplayDamageNumbersEvent>,
+    mut health_update_events: EventWriter<HealthUpdateEvent>,
+    mut q_health: Query<(Entity, &Transform, &mut Health)>,
+) {
+    for e in heal_events.read() {
+        if let Ok((entity, transform, mut health)) = q_health.get_mut(e.entity) {
+            // Dead entities can't be healed back to life
             if health.current() <= 0.0 {
-                enemy_events.send(EnemyDefeatedEvent);
-                commands.entity(entity).despawn_recursive();
+                continue;
             }
+            let overheal = health.heal(e.amount);
+            let amount = e.amount - overheal;
+            heal_dealt_events.send(HealDealtEvent {
+                source: e.source,
+                target: entity,
+                ability: e.ability,
+                amount,
+                overheal,
+            });
+            if amount <= 0.0 {
+                continue;
+            }
+            display_damage_events.send(DisplayDamageNumbersEvent {
+                damage: amount,
+                position: *transform,
+                is_crit: false,
+                is_heal: true,
+                entity,
+                ability: e.ability,
+            });
+            health_update_events.send(HealthUpdateEvent {
+                entity,
+                total_health: health.max(),
+                new_health: health.current(),
+            });
+        }
+    }
+}
+
+/// Marks entities whose health reached 0 as dead, each entity dies exactly once
+/// no matter how many lethal hits it took
+#[allow(clippy::type_complexity)]
+fn detect_deaths(
+    mut commands: Commands,
+    mut enemy_events: EventWriter<EnemyDefeatedEvent>,
+    q_health: Query<(Entity, &Health, Has<Enemy>), (Changed<Health>, Without<Dead>)>,
+) {
+    for (entity, health, is_enemy) in q_health.iter() {
+        if health.current() > 0.0 {
+            continue;
+        }
+        commands.entity(entity).insert(Dead);
+        if is_enemy {
+            enemy_events.send(EnemyDefeatedEvent { entity });
+        }
+    }
+}
+
+/// Decays knockbacks, the movement itself is applied by the kinematics integration
+fn apply_knockback(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Knockback)>,
+    time: Res<Time>,
+) {
+    for (entity, mut knockback) in query.iter_mut() {
+        **knockback *= (-KNOCKBACK_DECAY * time.delta_seconds()).exp();
+        if knockback.length() < KNOCKBACK_MIN_SPEED {
+            commands.entity(entity).remove::<Knockback>();
         }
     }
 }
+
+fn tick_hit_stun(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut HitStun)>,
+    time: Res<Time>,
+) {
+    for (entity, mut stun) in query.iter_mut() {
+        if stun.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<HitStun>();
+        }
+    }
+}
+
+fn hit_flash(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Sprite, &mut HitFlash, Option<&Tint>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut flash, tint) in query.iter_mut() {
+        if flash.tick(time.delta()).finished() {
+            sprite.color = tint.map_or(Color::WHITE, |tint| **tint);
+            commands.entity(entity).remove::<HitFlash>();
+        } else {
+            sprite.color = HIT_FLASH_COLOR;
+        }
+    }
+}
+
+/// Ticks timed invulnerabilities and makes the entity blink while it lasts
+fn tick_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut visibility) in query.iter_mut() {
+        let Some(timer) = invulnerable.0.as_mut() else {
+            continue;
+        };
+        if timer.tick(time.delta()).finished() {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else {
+            let blink = (timer.elapsed_secs() / BLINK_INTERVAL) as u32 % 2 == 1;
+            *visibility = if blink {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exp::{Experience, ExperiencePlugin, Level};
+    use crate::player::Player;
+    use bevy::ecs::event::ManualEventReader;
+
+    fn setup_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(DamagePlugin)
+            .add_event::<EnemyDefeatedEvent>();
+        app
+    }
+
+    fn spawn_enemy(app: &mut App, health: f32) -> Entity {
+        app.world
+            .spawn((Enemy::default(), Health::new(health), Transform::default()))
+            .id()
+    }
+
+    fn hit(app: &mut App, entity: Entity, damage: f32) {
+        app.world.send_event(DamageEvent {
+            damage,
+            crit_hit: CriticalHit::new(0.0, 1.0),
+            entity,
+            source: None,
+            ability: AbilityKind::Fireball,
+            knockback: None,
+        });
+    }
+
+    fn defeated(app: &App, reader: &mut ManualEventReader<EnemyDefeatedEvent>) -> Vec<Entity> {
+        let events = app.world.resource::<Events<EnemyDefeatedEvent>>();
+        reader.read(events).map(|e| e.entity).collect()
+    }
+
+    #[test]
+    fn several_lethal_hits_in_one_frame_defeat_once() {
+        let mut app = setup_app();
+        let mut reader = ManualEventReader::default();
+        let enemy = spawn_enemy(&mut app, 50.0);
+        for _ in 0..3 {
+            hit(&mut app, enemy, 100.0);
+        }
+        app.update();
+
+        assert_eq!(defeated(&app, &mut reader), vec![enemy]);
+        assert!(app.world.get::<Dead>(enemy).is_some());
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current(), 0.0);
+    }
+
+    #[test]
+    fn hits_on_dead_enemy_are_ignored() {
+        let mut app = setup_app();
+        let mut reader = ManualEventReader::default();
+        let enemy = spawn_enemy(&mut app, 50.0);
+        hit(&mut app, enemy, 100.0);
+        app.update();
+        assert_eq!(defeated(&app, &mut reader).len(), 1);
+
+        hit(&mut app, enemy, 100.0);
+        app.update();
+        app.update();
+
+        assert!(defeated(&app, &mut reader).is_empty());
+        // The corpse is kept around, despawning is handled by the enemy plugin
+        assert!(app.world.get_entity(enemy).is_some());
+    }
+
+    #[test]
+    fn non_lethal_hits_do_not_defeat() {
+        let mut app = setup_app();
+        let mut reader = ManualEventReader::default();
+        let enemy = spawn_enemy(&mut app, 50.0);
+        hit(&mut app, enemy, 20.0);
+        hit(&mut app, enemy, 20.0);
+        app.update();
+
+        assert!(defeated(&app, &mut reader).is_empty());
+        assert!(app.world.get::<Dead>(enemy).is_none());
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current(), 10.0);
+    }
+
+    #[test]
+    fn enemies_killed_in_the_same_frame_are_each_defeated_once() {
+        let mut app = setup_app();
+        let mut reader = ManualEventReader::default();
+        let first = spawn_enemy(&mut app, 50.0);
+        let second = spawn_enemy(&mut app, 50.0);
+        for _ in 0..2 {
+            hit(&mut app, first, 60.0);
+            hit(&mut app, second, 60.0);
+        }
+        app.update();
+
+        let mut defeated = defeated(&app, &mut reader);
+        defeated.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(defeated, expected);
+    }
+
+    #[test]
+    fn overkill_is_not_dealt() {
+        let mut app = setup_app();
+        let mut reader = ManualEventReader::<HealEvent>::default();
+        let attacker = app.world.spawn(Lifesteal::new(0.5)).id();
+        let enemy = spawn_enemy(&mut app, 10.0);
+        app.world.send_event(DamageEvent {
+            damage: 500.0,
+            crit_hit: CriticalHit::new(0.0, 1.0),
+            entity: enemy,
+            source: Some(attacker),
+            ability: AbilityKind::Fireball,
+            knockback: None,
+        });
+        app.update();
+
+        let heals = app.world.resource::<Events<HealEvent>>();
+        let healed: Vec<f32> = reader.read(heals).map(|e| e.amount).collect();
+        assert_eq!(healed, vec![5.0]);
+    }
+
+    #[test]
+    fn experience_is_awarded_once_per_enemy() {
+        let mut app = setup_app();
+        app.add_plugins(ExperiencePlugin);
+        let player = app
+            .world
+            .spawn((Player::default(), Experience::new(0.0), Level::new(1)))
+            .id();
+        let enemy = spawn_enemy(&mut app, 50.0);
+        for _ in 0..4 {
+            hit(&mut app, enemy, 100.0);
+        }
+        app.update();
+        app.update();
+
+        assert_eq!(app.world.get::<Experience>(player).unwrap().current(), 25.0);
+    }
+}
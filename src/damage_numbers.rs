@@ -0,0 +1,212 @@
+use crate::damage::{DamageType, DisplayDamageNumbersEvent};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+
+/// Scale applied when a number appears or grows, eased back over `POP_DURATION`
+const POP_SCALE: f32 = 1.3;
+const POP_DURATION: f32 = 0.15;
+/// Fraction of the lifetime after which the number starts fading out
+const FADE_START: f32 = 0.5;
+
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DamageNumberSettings>()
+            .init_resource::<DamageNumberSettings>()
+            .add_systems(Update, display_damage_numbers)
+            .add_systems(Update, animate_damage_numbers);
+    }
+}
+
+#[derive(Debug, Clone, Reflect)]
+pub struct DamageNumberStyle {
+    pub color: Color,
+    pub font_size: f32,
+}
+
+impl DamageNumberStyle {
+    pub fn new(color: Color, font_size: f32) -> Self {
+        Self { color, font_size }
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+/// Damage and heal numbers settings, editable from the inspector
+pub struct DamageNumberSettings {
+    pub enabled: bool,
+    pub font: Handle<Font>,
+    pub physical: DamageNumberStyle,
+    pub fire: DamageNumberStyle,
+    pub heal: DamageNumberStyle,
+    pub crit_color: Color,
+    /// Scale of critical hit numbers
+    pub crit_scale: f32,
+    /// Rising speed in pixels per second
+    pub rise_speed: f32,
+    /// Time a number stays on screen, in seconds
+    pub lifetime: f32,
+    /// Hits on the same target within this window (in seconds) are merged in one number
+    pub merge_window: f32,
+}
+
+impl Default for DamageNumberSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            font: Handle::default(),
+            physical: DamageNumberStyle::new(Color::WHITE, 20.0),
+            fire: DamageNumberStyle::new(Color::rgb(1.0, 0.55, 0.1), 20.0),
+            heal: DamageNumberStyle::new(Color::GREEN, 20.0),
+            crit_color: Color::YELLOW,
+            crit_scale: 1.4,
+            rise_speed: 40.0,
+            lifetime: 0.8,
+            merge_window: 0.3,
+        }
+    }
+}
+
+impl DamageNumberSettings {
+    pub fn style(&self, kind: NumberKind) -> &DamageNumberStyle {
+        match kind {
+            NumberKind::Damage(DamageType::Physical) => &self.physical,
+            NumberKind::Damage(DamageType::Fire) => &self.fire,
+            NumberKind::Heal => &self.heal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    Damage(DamageType),
+    Heal,
+}
+
+#[derive(Component)]
+/// Floating number showing the running total of recent hits (or heals) on a target
+pub struct DamageNumber {
+    pub target: Entity,
+    pub kind: NumberKind,
+    pub total: f32,
+    pub is_crit: bool,
+    pub age: Timer,
+}
+
+impl DamageNumber {
+    fn text(&self) -> String {
+        match self.kind {
+            NumberKind::Heal => format!("+{:.0}", self.total),
+            NumberKind::Damage(_) => format!("{:.0}", self.total),
+        }
+    }
+    fn color(&self, settings: &DamageNumberSettings) -> Color {
+        if self.is_crit {
+            settings.crit_color
+        } else {
+            settings.style(self.kind).color
+        }
+    }
+    fn scale(&self, settings: &DamageNumberSettings) -> f32 {
+        let base = if self.is_crit {
+            settings.crit_scale
+        } else {
+            1.0
+        };
+        let pop = (1.0 - self.age.elapsed_secs() / POP_DURATION).max(0.0);
+        base * (1.0 + (POP_SCALE - 1.0) * pop)
+    }
+}
+
+fn display_damage_numbers(
+    mut commands: Commands,
+    mut events: EventReader<DisplayDamageNumbersEvent>,
+    mut q_numbers: Query<&mut DamageNumber>,
+    settings: Res<DamageNumberSettings>,
+) {
+    if !settings.enabled {
+        events.clear();
+        return;
+    }
+    // Hits of this frame are grouped first, so that simultaneous hits also merge
+    let mut hits: HashMap<(Entity, NumberKind), (f32, bool, Vec3)> = HashMap::new();
+    for event in events.read() {
+        let kind = if event.is_heal {
+            NumberKind::Heal
+        } else {
+            NumberKind::Damage(event.ability.damage_type())
+        };
+        let hit =
+            hits.entry((event.entity, kind))
+                .or_insert((0.0, false, event.position.translation));
+        hit.0 += event.damage;
+        hit.1 |= event.is_crit;
+    }
+    for ((target, kind), (amount, is_crit, position)) in hits {
+        let merged = q_numbers.iter_mut().find(|number| {
+            number.target == target
+                && number.kind == kind
+                && number.age.elapsed_secs() <= settings.merge_window
+        });
+        if let Some(mut number) = merged {
+            number.total += amount;
+            number.is_crit |= is_crit;
+            number.age.reset();
+            continue;
+        }
+        let mut rng = rand::thread_rng();
+        let x = position.x + rng.gen_range(-10.0..10.0);
+        let y = position.y + rng.gen_range(-10.0..10.0);
+        let number = DamageNumber {
+            target,
+            kind,
+            total: amount,
+            is_crit,
+            age: Timer::from_seconds(settings.lifetime, TimerMode::Once),
+        };
+        let style = settings.style(kind);
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    number.text(),
+                    TextStyle {
+                        font: settings.font.clone(),
+                        font_size: style.font_size,
+                        color: number.color(&settings),
+                    },
+                ),
+                transform: Transform::from_xyz(x, y, 2.0)
+                    .with_scale(Vec3::splat(number.scale(&settings))),
+                ..default()
+            },
+            number,
+        ));
+    }
+}
+
+fn animate_damage_numbers(
+    mut commands: Commands,
+    mut q_numbers: Query<(Entity, &mut DamageNumber, &mut Text, &mut Transform)>,
+    settings: Res<DamageNumberSettings>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut text, mut transform) in q_numbers.iter_mut() {
+        if number.age.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let progress = number.age.fraction();
+        let alpha = if progress < FADE_START {
+            1.0
+        } else {
+            1.0 - (progress - FADE_START) / (1.0 - FADE_START)
+        };
+        let section = &mut text.sections[0];
+        section.value = number.text();
+        section.style.color = number.color(&settings).with_a(alpha);
+        transform.translation.y += settings.rise_speed * time.delta_seconds();
+        transform.scale = Vec3::splat(number.scale(&settings));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::enemy::EnemyType;
+use crate::tiled::{MapEnemySpawn, MapTarget, TiledMap, ZoneTransition};
+use crate::tilemap::{TileKind, TileMap};
+use crate::TILE_SIZE;
+use bevy::math::IRect;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Size of a floor, in tiles
+const FLOOR_SIZE: IVec2 = IVec2::new(48, 36);
+/// Areas are not split below this size, in tiles
+const MIN_AREA_SIZE: i32 = 10;
+const MIN_ROOM_SIZE: i32 = 4;
+/// Level of the enemies on the first floor, one more on every floor below
+const FIRST_FLOOR_LEVEL: u32 = 2;
+const MAX_ENEMIES_PER_ROOM: u32 = 5;
+/// Environment variable setting the seed of the run, to replay the same floors
+const SEED_VARIABLE: &str = "NBOL_DUNGEON_SEED";
+/// Enemies found in the dungeon, with their weight
+const DUNGEON_ENEMIES: [(EnemyType, u32); 4] = [
+    (EnemyType::Skeleton, 4),
+    (EnemyType::SkeletonArcher, 2),
+    (EnemyType::Zombie, 3),
+    (EnemyType::Slime, 2),
+];
+
+pub struct DungeonPlugin;
+
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DungeonSeed>()
+            .insert_resource(DungeonSeed::from_env())
+            .add_systems(Startup, log_dungeon_seed);
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone, Copy)]
+#[reflect(Resource)]
+/// Seed of the current run, every floor is generated from it and its depth
+pub struct DungeonSeed(pub u64);
+
+impl Default for DungeonSeed {
+    fn default() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl DungeonSeed {
+    /// Seed set by `SEED_VARIABLE`, or a random one when it is missing or invalid
+    fn from_env() -> Self {
+        let Ok(value) = std::env::var(SEED_VARIABLE) else {
+            return Self::default();
+        };
+        value.trim().parse().map(Self).unwrap_or_else(|_| {
+            warn!("Invalid {SEED_VARIABLE} {value:?}, using a random seed");
+            Self::default()
+        })
+    }
+}
+
+fn log_dungeon_seed(seed: Res<DungeonSeed>) {
+    info!(
+        "Dungeon seed {}, replay it with {SEED_VARIABLE}={}",
+        seed.0, seed.0
+    );
+}
+
+/// Generates the floor at `depth` (starting at 1): rooms split from the floor area and joined
+/// by corridors, enemies leveled by depth and stairs to the next floor in the room farthest from the start
+pub fn generate_floor(seed: u64, depth: u32) -> TiledMap {
+    let mut rng = StdRng::seed_from_u64(seed ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let mut tiles = TileMap::filled(Vec2::ZERO, FLOOR_SIZE.as_uvec2(), TileKind::Wall);
+    let mut rooms = Vec::new();
+    split(
+        &mut rng,
+        IRect::from_corners(IVec2::ZERO, FLOOR_SIZE),
+        &mut tiles,
+        &mut rooms,
+    );
+
+    let start = rooms[0].center();
+    let distances = distances_from(&tiles, start);
+    let stairs = rooms
+        .iter()
+        .map(IRect::center)
+        .max_by_key(|center| distances[index(*center)].unwrap_or(0))
+        .unwrap_or(start);
+    tiles.set(stairs, TileKind::Stairs);
+
+    let level = FIRST_FLOOR_LEVEL + depth - 1;
+    let per_room = (1 + depth / 2).min(MAX_ENEMIES_PER_ROOM);
+    let mut enemy_spawns = Vec::new();
+    for room in rooms.iter().filter(|room| !room.contains(start)) {
+        for _ in 0..rng.gen_range(1..=per_room) {
+            let tile = IVec2::new(
+                rng.gen_range(room.min.x..room.max.x),
+                rng.gen_range(room.min.y..room.max.y),
+            );
+            let Ok((enemy_type, _)) = DUNGEON_ENEMIES.choose_weighted(&mut rng, |(_, w)| *w) else {
+                continue;
+            };
+            enemy_spawns.push(MapEnemySpawn {
+                enemy_type: *enemy_type,
+                level: Some(level),
+                position: tiles.tile_center(tile),
+                respawn: None,
+            });
+        }
+    }
+
+    let stairs_area = Rect::from_center_size(tiles.tile_center(stairs), Vec2::splat(TILE_SIZE));
+    TiledMap {
+        player_spawn: Some(tiles.tile_center(start)),
+        enemy_spawns,
+        transitions: vec![ZoneTransition {
+            area: stairs_area,
+            target: MapTarget::DungeonFloor(depth + 1),
+        }],
+        tiles,
+    }
+}
+
+/// Splits `area` in two until too small, carving a room in each leaf and joining
+/// the two halves with a corridor. The rooms are added to `rooms`
+fn split(rng: &mut StdRng, area: IRect, tiles: &mut TileMap, rooms: &mut Vec<IRect>) {
+    let size = area.size();
+    let horizontal = match (size.x >= MIN_AREA_SIZE * 2, size.y >= MIN_AREA_SIZE * 2) {
+        (false, false) => {
+            let room = carve_room(rng, area, tiles);
+            rooms.push(room);
+            return;
+        }
+        (true, false) => false,
+        (false, true) => true,
+        (true, true) => rng.gen_bool(0.5),
+    };
+    let (first, second) = if horizontal {
+        let cut = rng.gen_range(area.min.y + MIN_AREA_SIZE..=area.max.y - MIN_AREA_SIZE);
+        (
+            IRect::new(area.min.x, area.min.y, area.max.x, cut),
+            IRect::new(area.min.x, cut, area.max.x, area.max.y),
+        )
+    } else {
+        let cut = rng.gen_range(area.min.x + MIN_AREA_SIZE..=area.max.x - MIN_AREA_SIZE);
+        (
+            IRect::new(area.min.x, area.min.y, cut, area.max.y),
+            IRect::new(cut, area.min.y, area.max.x, area.max.y),
+        )
+    };
+    let first_rooms = rooms.len();
+    split(rng, first, tiles, rooms);
+    let second_rooms = rooms.len();
+    split(rng, second, tiles, rooms);
+    // Every split is joined once, so that all the rooms are connected
+    let from = rooms[rng.gen_range(first_rooms..second_rooms)].center();
+    let to = rooms[rng.gen_range(second_rooms..rooms.len())].center();
+    carve_corridor(rng, from, to, tiles);
+}
+
+/// Carves a room inside `area`, keeping a wall between the room and the area edges
+fn carve_room(rng: &mut StdRng, area: IRect, tiles: &mut TileMap) -> IRect {
+    let size = IVec2::new(
+        rng.gen_range(MIN_ROOM_SIZE..=area.width() - 2),
+        rng.gen_range(MIN_ROOM_SIZE..=area.height() - 2),
+    );
+    let min = IVec2::new(
+        rng.gen_range(area.min.x + 1..=area.max.x - 1 - size.x),
+        rng.gen_range(area.min.y + 1..=area.max.y - 1 - size.y),
+    );
+    let room = IRect::from_corners(min, min + size);
+    for y in room.min.y..room.max.y {
+        for x in room.min.x..room.max.x {
+            tiles.set(IVec2::new(x, y), TileKind::Floor);
+        }
+    }
+    room
+}
+
+/// Carves an L shaped corridor between two tiles
+fn carve_corridor(rng: &mut StdRng, from: IVec2, to: IVec2, tiles: &mut TileMap) {
+    let corner = if rng.gen_bool(0.5) {
+        IVec2::new(to.x, from.y)
+    } else {
+        IVec2::new(from.x, to.y)
+    };
+    for (start, end) in [(from, corner), (corner, to)] {
+        let (min, max) = (start.min(end), start.max(end));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                tiles.set(IVec2::new(x, y), TileKind::Floor);
+            }
+        }
+    }
+}
+
+fn index(tile: IVec2) -> usize {
+    (tile.y * FLOOR_SIZE.x + tile.x) as usize
+}
+
+/// Walking distance of every tile of the floor from `start`, in tiles
+fn distances_from(tiles: &TileMap, start: IVec2) -> Vec<Option<u32>> {
+    let mut distances = vec![None; (FLOOR_SIZE.x * FLOOR_SIZE.y) as usize];
+    distances[index(start)] = Some(0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(tile) = queue.pop_front() {
+        let distance = distances[index(tile)].unwrap_or(0);
+        for step in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let next = tile + step;
+            let inside = next.cmpge(IVec2::ZERO).all() && next.cmplt(FLOOR_SIZE).all();
+            if !inside || tiles.get(next).blocks_movement() || distances[index(next)].is_some() {
+                continue;
+            }
+            distances[index(next)] = Some(distance + 1);
+            queue.push_back(next);
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_floor() {
+        assert_eq!(generate_floor(42, 3), generate_floor(42, 3));
+        assert_ne!(generate_floor(42, 3), generate_floor(42, 4));
+        assert_ne!(generate_floor(42, 3), generate_floor(43, 3));
+    }
+
+    #[test]
+    fn stairs_are_reachable() {
+        for seed in 0..50 {
+            let floor = generate_floor(seed, 1);
+            let start = floor.tiles.tile_at(floor.player_spawn.unwrap());
+            let stairs = floor.tiles.tile_at(floor.transitions[0].area.center());
+            assert_ne!(start, stairs, "seed {seed}");
+            let distances = distances_from(&floor.tiles, start);
+            assert!(distances[index(stairs)].is_some(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn enemies_level_up_with_depth() {
+        let level = |depth| generate_floor(7, depth).enemy_spawns[0].level;
+        assert_eq!(level(1), Some(FIRST_FLOOR_LEVEL));
+        assert_eq!(level(4), Some(FIRST_FLOOR_LEVEL + 3));
+    }
+}
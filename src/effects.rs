@@ -0,0 +1,152 @@
+use crate::abilities::AbilityKind;
+use crate::damage::{CriticalHit, Damage, DamageEvent, HealEvent};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyEffectEvent>()
+            .add_systems(Update, (apply_effects, tick_effects).chain());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EffectKind {
+    Damage,
+    Heal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How the amount of each tick is computed
+pub enum TickAmount {
+    /// Fixed amount per tick, computed when the effect is created
+    Snapshot(f32),
+    /// Ratio of the source's current `Damage`, computed on every tick
+    Dynamic(f32),
+}
+
+#[derive(Debug, Clone)]
+/// An effect that damages or heals its target every `tick` for a total `duration`
+pub struct PeriodicEffect {
+    pub kind: EffectKind,
+    pub ability: AbilityKind,
+    /// Entity the ticks are attributed to
+    pub source: Option<Entity>,
+    pub amount: TickAmount,
+    pub tick: Timer,
+    pub duration: Timer,
+}
+
+impl PeriodicEffect {
+    pub fn new(
+        kind: EffectKind,
+        ability: AbilityKind,
+        amount: TickAmount,
+        interval: f32,
+        duration: f32,
+    ) -> Self {
+        Self {
+            kind,
+            ability,
+            source: None,
+            amount,
+            tick: Timer::from_seconds(interval, TimerMode::Repeating),
+            duration: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+#[derive(Component, Default, Deref, DerefMut)]
+/// Periodic effects currently active on an entity
+pub struct PeriodicEffects(Vec<PeriodicEffect>);
+
+#[derive(Component, Deref, DerefMut)]
+/// Effect applied to whatever the entity (e.g. a projectile) hits
+pub struct OnHitEffect(pub PeriodicEffect);
+
+#[derive(Event)]
+pub struct ApplyEffectEvent {
+    pub target: Entity,
+    pub effect: PeriodicEffect,
+}
+
+/// Adds new effects to their targets, re-applying an effect from the same source refreshes it
+fn apply_effects(
+    mut commands: Commands,
+    mut events: EventReader<ApplyEffectEvent>,
+    mut q_effects: Query<&mut PeriodicEffects>,
+) {
+    // Targets without effects yet, the component is inserted once all events are read
+    let mut new_effects: HashMap<Entity, PeriodicEffects> = HashMap::new();
+    for event in events.read() {
+        let effects = match q_effects.get_mut(event.target) {
+            Ok(effects) => effects.into_inner(),
+            Err(_) => new_effects.entry(event.target).or_default(),
+        };
+        effects.retain(|effect| {
+            effect.ability != event.effect.ability || effect.source != event.effect.source
+        });
+        effects.push(event.effect.clone());
+    }
+    for (target, effects) in new_effects {
+        if let Some(mut target) = commands.get_entity(target) {
+            target.try_insert(effects);
+        }
+    }
+}
+
+fn tick_effects(
+    mut commands: Commands,
+    mut q_effects: Query<(Entity, &mut PeriodicEffects)>,
+    q_damage: Query<&Damage>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut heal_events: EventWriter<HealEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut effects) in q_effects.iter_mut() {
+        for effect in effects.iter_mut() {
+            effect.tick.tick(time.delta());
+            let amount = match effect.amount {
+                TickAmount::Snapshot(amount) => amount,
+                TickAmount::Dynamic(ratio) => effect
+                    .source
+                    .and_then(|source| q_damage.get(source).ok())
+                    .map_or(0.0, |damage| **damage * ratio),
+            };
+            for _ in 0..effect.tick.times_finished_this_tick() {
+                match effect.kind {
+                    EffectKind::Damage => {
+                        damage_events.send(DamageEvent {
+                            damage: amount,
+                            // Ticks can't crit
+                            crit_hit: CriticalHit::new(0.0, 1.0),
+                            entity,
+                            source: effect.source,
+                            ability: effect.ability,
+                            knockback: None,
+                        });
+                    }
+                    EffectKind::Heal => {
+                        heal_events.send(HealEvent {
+                            amount,
+                            entity,
+                            source: effect.source,
+                            ability: effect.ability,
+                        });
+                    }
+                }
+            }
+            effect.duration.tick(time.delta());
+        }
+        effects.retain(|effect| !effect.duration.finished());
+        if effects.is_empty() {
+            commands.entity(entity).remove::<PeriodicEffects>();
+        }
+    }
+}
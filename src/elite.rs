@@ -0,0 +1,149 @@
+use crate::abilities::AbilityKind;
+use crate::damage::{DamageDealtEvent, Lifesteal};
+use crate::effects::{ApplyEffectEvent, EffectKind, PeriodicEffect, TickAmount};
+use crate::enemy::{set_nameplate, Enemy, EnemyNameUI};
+use crate::entities::Health;
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Chance for a regular enemy to spawn as an elite
+pub const ELITE_CHANCE: f64 = 0.1;
+const MAX_AFFIXES: usize = 2;
+const ELITE_NAME_COLOR: Color = Color::rgb(0.35, 0.65, 1.0);
+const FAST_SPEED_MULTIPLIER: f32 = 1.5;
+const EXTRA_HEALTH_MULTIPLIER: f32 = 2.0;
+const VAMPIRIC_LIFESTEAL: f32 = 0.3;
+/// Burn damage per tick of fire enchanted hits, relative to the hit damage
+const FIRE_ENCHANTED_BURN_RATIO: f32 = 0.1;
+const FIRE_ENCHANTED_BURN_DURATION: f32 = 3.0;
+
+pub struct ElitePlugin;
+
+impl Plugin for ElitePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Elite>()
+            .register_type::<EliteAffix>()
+            .add_systems(Update, apply_elite_affixes)
+            .add_systems(Update, fire_enchanted_hits);
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EliteAffix {
+    Fast,
+    ExtraHealth,
+    FireEnchanted,
+    Vampiric,
+}
+
+impl EliteAffix {
+    const ALL: [EliteAffix; 4] = [
+        EliteAffix::Fast,
+        EliteAffix::ExtraHealth,
+        EliteAffix::FireEnchanted,
+        EliteAffix::Vampiric,
+    ];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            EliteAffix::Fast => "Fast",
+            EliteAffix::ExtraHealth => "Tough",
+            EliteAffix::FireEnchanted => "Fire Enchanted",
+            EliteAffix::Vampiric => "Vampiric",
+        }
+    }
+}
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+/// Stronger variant of an enemy, affixes are applied when the component is added
+pub struct Elite {
+    pub affixes: Vec<EliteAffix>,
+}
+
+impl Elite {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let count = rng.gen_range(1..=MAX_AFFIXES);
+        Self {
+            affixes: EliteAffix::ALL
+                .choose_multiple(rng, count)
+                .copied()
+                .collect(),
+        }
+    }
+    pub fn has(&self, affix: EliteAffix) -> bool {
+        self.affixes.contains(&affix)
+    }
+}
+
+pub fn apply_elite_affixes(
+    mut commands: Commands,
+    mut q_elites: Query<(Entity, &Elite, &mut Enemy, &mut Health, &Children), Added<Elite>>,
+    mut q_names: Query<&mut Text, With<EnemyNameUI>>,
+) {
+    for (entity, elite, mut enemy, mut health, children) in q_elites.iter_mut() {
+        for affix in elite.affixes.iter() {
+            match affix {
+                EliteAffix::Fast => enemy.speed *= FAST_SPEED_MULTIPLIER,
+                EliteAffix::ExtraHealth => {
+                    *health = Health::new(health.max() * EXTRA_HEALTH_MULTIPLIER)
+                }
+                EliteAffix::Vampiric => {
+                    commands
+                        .entity(entity)
+                        .insert(Lifesteal::new(VAMPIRIC_LIFESTEAL));
+                }
+                // Applied on hit by `fire_enchanted_hits`
+                EliteAffix::FireEnchanted => {}
+            }
+        }
+        let prefix = elite
+            .affixes
+            .iter()
+            .map(|affix| affix.display_name())
+            .collect::<Vec<_>>()
+            .join(" ");
+        enemy.display_name = format!("{prefix} {}", enemy.display_name);
+        set_nameplate(
+            children,
+            &mut q_names,
+            &enemy.display_name,
+            ELITE_NAME_COLOR,
+        );
+    }
+}
+
+/// Hits from fire enchanted elites set their target on fire
+fn fire_enchanted_hits(
+    mut events: EventReader<DamageDealtEvent>,
+    mut effect_events: EventWriter<ApplyEffectEvent>,
+    q_elites: Query<&Elite>,
+) {
+    for event in events.read() {
+        // Burn ticks must not apply burn again
+        if event.ability == AbilityKind::Burn {
+            continue;
+        }
+        let Some(source) = event.source else {
+            continue;
+        };
+        if !q_elites
+            .get(source)
+            .is_ok_and(|elite| elite.has(EliteAffix::FireEnchanted))
+        {
+            continue;
+        }
+        effect_events.send(ApplyEffectEvent {
+            target: event.target,
+            effect: PeriodicEffect::new(
+                EffectKind::Damage,
+                AbilityKind::Burn,
+                TickAmount::Snapshot(event.damage * FIRE_ENCHANTED_BURN_RATIO),
+                1.0,
+                FIRE_ENCHANTED_BURN_DURATION,
+            )
+            .with_source(source),
+        });
+    }
+}
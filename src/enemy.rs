@@ -1,26 +1,55 @@
+use crate::abilities::AbilityKind;
+use crate::ai::EnemyAi;
+use crate::boss::Boss;
+use crate::chunks::Dormant;
+use crate::collision::{Collider, ENEMY_LAYER};
 use crate::damage::{CriticalHit, Damage};
-use crate::entities::{get_facing_direction, Facing, FrameAnimation, Health, HealthUpdateEvent};
-use crate::player::Player;
+use crate::enemy_catalog::{
+    catalog_loaded, EnemyCatalog, EnemyCatalogHandle, EnemyDefinition, EnemySprite,
+};
+use crate::entities::{Dead, Facing, FrameAnimation, Health, HealthUpdateEvent, Tint};
+use crate::faction::Faction;
+use crate::kinematics::Velocity;
+use crate::pathfinding::PathFollower;
+use crate::threat::ThreatTable;
 use crate::TILE_SIZE;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-use rand::{thread_rng, Rng};
+use bevy::utils::HashMap;
+use serde::Deserialize;
 
-const COLUMNS: usize = 13;
-const ROWS: usize = 21;
-const ENEMY_COUNT: usize = 50;
-const ENEMY_FRAMES: usize = 9;
+/// Health and damage gained per level above the catalog level
+const LEVEL_SCALING: f32 = 0.1;
+/// Lowest health and damage multiplier of enemies below their catalog level
+const MIN_LEVEL_SCALING: f32 = 0.5;
+const DEATH_ANIMATION_SPEED: f32 = 0.1;
+/// Time a corpse stays on the ground before despawning, in seconds
+const CORPSE_LIFETIME: f32 = 4.0;
+/// Time a corpse takes to fade out at the end of its lifetime, in seconds
+const CORPSE_FADE: f32 = 1.0;
+/// Distance under which an enemy is considered back at its spawn
+pub const SPAWN_REACHED_DISTANCE: f32 = 2.0;
 
-#[derive(Component)]
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Key of the enemy definition in the `EnemyCatalog`
 pub enum EnemyType {
     Skeleton,
+    SkeletonArcher,
+    Zombie,
+    Slime,
+    SkeletonLord,
 }
 
-impl EnemyType {
-    pub fn display_name(&self) -> String {
-        match self {
-            EnemyType::Skeleton => "Skeleton".to_string(),
-        }
+#[derive(Component, Debug, Deref)]
+/// Abilities of an enemy, from its catalog definition
+pub struct EnemyAbilities(Vec<AbilityKind>);
+
+impl EnemyAbilities {
+    pub fn new(abilities: Vec<AbilityKind>) -> Self {
+        Self(abilities)
+    }
+    /// Ability used for basic attacks
+    pub fn attack(&self) -> AbilityKind {
+        self.first().copied().unwrap_or(AbilityKind::Melee)
     }
 }
 
@@ -31,6 +60,21 @@ pub struct EnemyHealthBackgroundUI;
 #[derive(Component)]
 pub struct EnemyHealthForegroundUI;
 
+/// Changes the text and color of an enemy nameplate
+pub fn set_nameplate(
+    children: &Children,
+    q_names: &mut Query<&mut Text, With<EnemyNameUI>>,
+    name: &str,
+    color: Color,
+) {
+    for child in children.iter() {
+        if let Ok(mut text) = q_names.get_mut(*child) {
+            text.sections[0].value = name.to_string();
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
 pub fn update_hp_ui(
     sprite: &mut Sprite,
     transform: &mut Transform,
@@ -47,21 +91,68 @@ pub fn update_hp_ui(
     transform.translation = Vec3::new(-TILE_SIZE / 2.0 + foreground_scale_x / 2.0, 0.0, 0.0);
 }
 
-#[derive(Resource, Clone)]
-pub struct SkeletonSheet {
+#[derive(Clone)]
+pub struct EnemySheet {
     pub handle: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
     pub up: Vec<usize>,
     pub down: Vec<usize>,
     pub left: Vec<usize>,
     pub right: Vec<usize>,
+    pub death: Vec<usize>,
 }
+
+impl EnemySheet {
+    fn new(
+        sprite: &EnemySprite,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let (width, height) = sprite.frame_size;
+        let layout = TextureAtlasLayout::from_grid(
+            Vec2::new(width, height),
+            sprite.columns,
+            sprite.rows,
+            None,
+            None,
+        );
+        let row = |row: usize, frames: usize| -> Vec<usize> {
+            (0..frames).map(|i| sprite.columns * row + i).collect()
+        };
+        Self {
+            handle: asset_server.load(sprite.path.clone()),
+            layout: layouts.add(layout),
+            up: row(sprite.walk_row, sprite.walk_frames),
+            left: row(sprite.walk_row + 1, sprite.walk_frames),
+            down: row(sprite.walk_row + 2, sprite.walk_frames),
+            right: row(sprite.walk_row + 3, sprite.walk_frames),
+            death: row(sprite.death_row, sprite.death_frames),
+        }
+    }
+    fn walk(&self, facing: Facing) -> &[usize] {
+        match facing {
+            Facing::Up => &self.up,
+            Facing::Down => &self.down,
+            Facing::Left => &self.left,
+            Facing::Right => &self.right,
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+/// Spritesheets of every enemy type, built once the enemy catalog is loaded
+pub struct EnemySheets(HashMap<EnemyType, EnemySheet>);
 #[derive(Component, Debug)]
 pub struct Enemy {
+    /// Movement speed in tiles per second
     pub speed: f32,
     pub level: u32,
     pub moving: bool,
     pub aggro_range: f32,
-    pub aggro: bool,
+    /// Maximum distance from `spawn_coords` before dropping combat
+    pub leash_range: f32,
+    /// Set when leashing, the enemy ignores threat until it is back at its spawn
+    pub evading: bool,
     pub spawn_coords: Vec3,
     pub display_name: String,
 }
@@ -70,8 +161,9 @@ impl Default for Enemy {
     fn default() -> Self {
         Self {
             spawn_coords: Vec3::new(0.0, 0.0, 0.0),
-            aggro: false,
             aggro_range: 150.0,
+            leash_range: 450.0,
+            evading: false,
             speed: 1.0,
             moving: false,
             display_name: "".to_string(),
@@ -83,15 +175,27 @@ impl Default for Enemy {
 pub struct EnemyBundle {
     pub enemy: Enemy,
     pub enemy_type: EnemyType,
+    pub abilities: EnemyAbilities,
     pub sprite: SpriteSheetBundle,
+    pub tint: Tint,
     pub animation: FrameAnimation,
     pub facing: Facing,
     pub health: Health,
     pub critical_hit: CriticalHit,
     pub damage: Damage,
+    pub threat: ThreatTable,
+    pub ai: EnemyAi,
+    pub path: PathFollower,
+    pub velocity: Velocity,
+    pub faction: Faction,
+    pub collider: Collider,
     // pub ui: EnemyUI,
 }
 
+#[derive(Component, Deref, DerefMut)]
+/// Remaining lifetime of a dead enemy's corpse
+pub struct Corpse(Timer);
+
 #[derive(Component)]
 pub struct EnemyUI {
     pub name: EnemyNameUI,
@@ -109,38 +213,131 @@ pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<EnemyDefeatedEvent>()
-            .add_systems(Startup, spawn_enemies)
-            .add_systems(Update, move_enemies)
+            .add_systems(
+                Update,
+                load_enemy_sheets
+                    .run_if(catalog_loaded.and_then(not(resource_exists::<EnemySheets>))),
+            )
             .add_systems(Update, animate_enemies)
-            .add_systems(Update, update_enemy_graphics)
-            .add_systems(Update, update_health_ui);
+            .add_systems(
+                Update,
+                update_enemy_graphics.run_if(resource_exists::<EnemySheets>),
+            )
+            .add_systems(Update, update_health_ui)
+            .add_systems(
+                Update,
+                (start_enemy_death, update_corpses)
+                    .chain()
+                    .run_if(resource_exists::<EnemySheets>),
+            );
     }
 }
 
 fn update_health_ui(
     mut events: EventReader<HealthUpdateEvent>,
+    mut woken: RemovedComponents<Dormant>,
     mut q_sprites: Query<(&mut Sprite, &mut Transform, &Parent), With<EnemyHealthForegroundUI>>,
-    mut q_background: Query<(&Parent, &Children), With<EnemyHealthBackgroundUI>>,
+    q_background: Query<(&Parent, &Children), With<EnemyHealthBackgroundUI>>,
+    q_dormant: Query<(), With<Dormant>>,
+    q_health: Query<&Health>,
 ) {
-    for event in events.read() {
-        for (parent, children) in q_background.iter_mut() {
-            if parent.get() == event.entity {
+    // Bars are not updated while dormant, they are refreshed when the enemy wakes up
+    let woken = woken.read().filter_map(|entity| {
+        q_health
+            .get(entity)
+            .ok()
+            .map(|health| (entity, health.max(), health.current()))
+    });
+    let updates = events
+        .read()
+        .map(|event| (event.entity, event.total_health, event.new_health))
+        .chain(woken)
+        .filter(|(entity, _, _)| !q_dormant.contains(*entity))
+        .collect::<Vec<_>>();
+    for (entity, total_health, new_health) in updates {
+        for (parent, children) in q_background.iter() {
+            if parent.get() == entity {
                 for child in children.iter() {
                     if let Ok(mut sprite) = q_sprites.get_mut(*child) {
-                        update_hp_ui(
-                            &mut sprite.0,
-                            &mut sprite.1,
-                            event.total_health,
-                            event.new_health,
-                        );
+                        update_hp_ui(&mut sprite.0, &mut sprite.1, total_health, new_health);
                     }
                 }
             }
         }
     }
 }
+#[allow(clippy::type_complexity)]
+fn start_enemy_death(
+    mut commands: Commands,
+    mut q_dead: Query<
+        (
+            Entity,
+            &EnemyType,
+            &mut Enemy,
+            &mut Velocity,
+            &mut FrameAnimation,
+            &Children,
+        ),
+        Added<Dead>,
+    >,
+    mut q_ui: Query<&mut Visibility, Or<(With<EnemyNameUI>, With<EnemyHealthBackgroundUI>)>>,
+    sheets: Res<EnemySheets>,
+) {
+    for (entity, enemy_type, mut enemy, mut velocity, mut animation, children) in q_dead.iter_mut()
+    {
+        enemy.moving = false;
+        **velocity = Vec2::ZERO;
+        if let Some(sheet) = sheets.get(enemy_type) {
+            animation.frames = sheet.death.to_vec();
+        }
+        animation.current_frame = 0;
+        animation.timer = Timer::from_seconds(DEATH_ANIMATION_SPEED, TimerMode::Repeating);
+        for child in children.iter() {
+            if let Ok(mut visibility) = q_ui.get_mut(*child) {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        commands.entity(entity).insert(Corpse(Timer::from_seconds(
+            CORPSE_LIFETIME,
+            TimerMode::Once,
+        )));
+    }
+}
+
+/// Plays the death animation once, then fades the corpse out before despawning it
+fn update_corpses(
+    mut commands: Commands,
+    mut q_corpses: Query<(
+        Entity,
+        &mut Corpse,
+        &mut TextureAtlas,
+        &mut FrameAnimation,
+        &mut Sprite,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, mut corpse, mut texture_atlas, mut animation, mut sprite) in q_corpses.iter_mut() {
+        if corpse.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        animation.timer.tick(time.delta());
+        if animation.timer.just_finished() && animation.current_frame + 1 < animation.frames.len() {
+            animation.current_frame += 1;
+        }
+        texture_atlas.index = animation.frames[animation.current_frame];
+        let remaining = corpse.remaining_secs();
+        if remaining < CORPSE_FADE {
+            sprite.color.set_a(remaining / CORPSE_FADE);
+        }
+    }
+}
+#[allow(clippy::type_complexity)]
 fn animate_enemies(
-    mut sprites_query: Query<(&mut TextureAtlas, &Enemy, &mut FrameAnimation), With<Enemy>>,
+    mut sprites_query: Query<
+        (&mut TextureAtlas, &Enemy, &mut FrameAnimation),
+        (Without<Dead>, Without<Dormant>),
+    >,
     time: Res<Time>,
 ) {
     for (mut texture_atlas, enemy, mut animation) in &mut sprites_query.iter_mut() {
@@ -157,202 +354,168 @@ fn animate_enemies(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn update_enemy_graphics(
-    mut sprites_query: Query<(&Facing, &mut FrameAnimation), With<Enemy>>,
-    char: Res<SkeletonSheet>,
-) {
-    for (facing, mut animation) in &mut sprites_query.iter_mut() {
-        animation.frames = match facing {
-            Facing::Up => char.up.to_vec(),
-            Facing::Down => char.down.to_vec(),
-            Facing::Left => char.left.to_vec(),
-            Facing::Right => char.right.to_vec(),
-        };
-    }
-}
-fn move_enemies(
-    mut query: Query<(&mut Transform, &mut Facing, &mut Enemy, &mut FrameAnimation), With<Enemy>>,
-    player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut sprites_query: Query<
+        (&Facing, &EnemyType, &mut FrameAnimation),
+        (Without<Dead>, Without<Dormant>),
+    >,
+    sheets: Res<EnemySheets>,
 ) {
-    let player_transform = player.single();
-    for (mut transform, mut facing, mut enemy, mut animation) in &mut query.iter_mut() {
-        let direction = player_transform.translation - transform.translation;
-        let distance = direction.length();
-
-        let direction_to_spawn = enemy.spawn_coords - transform.translation;
-        let distance_to_spawn = direction_to_spawn.length();
-
-        let aggro_range = if enemy.aggro {
-            enemy.aggro_range * 3.0
-        } else {
-            enemy.aggro_range
-        };
-        if distance <= aggro_range {
-            let direction = direction / distance;
-            let movement = direction * enemy.speed;
-            *facing = get_facing_direction(direction);
-            transform.translation += movement;
-            enemy.moving = true;
-            enemy.aggro = false;
-            animation.timer.unpause();
-        } else if distance_to_spawn > 2.0 {
-            let direction = direction_to_spawn / distance_to_spawn;
-            let movement = direction * enemy.speed * 4.0; // TODO evaluate: Move back multiplier
-            *facing = get_facing_direction(direction);
-            transform.translation += movement;
-            enemy.moving = true;
-            enemy.aggro = false;
-            animation.timer.unpause();
-        } else {
-            enemy.moving = false;
-            *facing = Facing::Down;
-            animation.timer.pause();
+    for (facing, enemy_type, mut animation) in &mut sprites_query.iter_mut() {
+        if let Some(sheet) = sheets.get(enemy_type) {
+            animation.frames = sheet.walk(*facing).to_vec();
         }
     }
 }
-
-fn spawn_enemies(
+/// Builds the spritesheet of every enemy type once the catalog is loaded
+fn load_enemy_sheets(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut textures: ResMut<Assets<TextureAtlasLayout>>,
-    window: Query<&Window, With<PrimaryWindow>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
 ) {
-    let window = window.single();
-    let texture_handle = asset_server.load("enemy.png");
-    let layout = TextureAtlasLayout::from_grid(Vec2::new(64.0, 64.0), COLUMNS, ROWS, None, None);
-    let atlas_handle = textures.add(layout);
-    let row_start = 8;
-    let enemy_up: Vec<usize> = (0..ENEMY_FRAMES)
-        .map(|i| COLUMNS * row_start + i)
-        .collect::<Vec<_>>();
-    let enemy_left: Vec<usize> = (0..ENEMY_FRAMES)
-        .map(|i| COLUMNS * (row_start + 1) + i)
-        .collect::<Vec<_>>();
-    let enemy_down: Vec<usize> = (0..ENEMY_FRAMES)
-        .map(|i| COLUMNS * (row_start + 2) + i)
-        .collect::<Vec<_>>();
-    let enemy_right: Vec<usize> = (0..ENEMY_FRAMES)
-        .map(|i| COLUMNS * (row_start + 3) + i)
-        .collect::<Vec<_>>();
-
-    let enemy_sheet = SkeletonSheet {
-        handle: texture_handle.clone(),
-        up: enemy_up.clone(),
-        down: enemy_down.clone(),
-        left: enemy_left.clone(),
-        right: enemy_right.clone(),
+    let Some(catalog) = catalogs.get(&**catalog) else {
+        return;
     };
-    commands.insert_resource(enemy_sheet);
+    commands.insert_resource(EnemySheets(
+        catalog
+            .enemies
+            .iter()
+            .map(|(enemy_type, definition)| {
+                let sheet = EnemySheet::new(&definition.sprite, &asset_server, &mut layouts);
+                (*enemy_type, sheet)
+            })
+            .collect(),
+    ));
+}
 
-    let mut rng = thread_rng();
-    for _ in 0..ENEMY_COUNT {
-        let x = rng.gen_range(0.0..window.width());
-        let y = rng.gen_range(0.0..window.height());
-        let facing = match rng.gen_range(0..4) {
-            0 => Facing::Up,
-            1 => Facing::Down,
-            2 => Facing::Left,
-            3 => Facing::Right,
-            _ => unreachable!(),
-        };
-        let initial_frame = match facing {
-            Facing::Up => enemy_up[0],
-            Facing::Down => enemy_down[0],
-            Facing::Left => enemy_left[0],
-            Facing::Right => enemy_right[0],
-        };
-        let coordinates = Vec3::new(x, y, 0.0);
-        let enemy_transform = Transform::from_translation(coordinates);
-        let sprite_bundle = SpriteSheetBundle {
-            texture: texture_handle.clone(),
-            atlas: TextureAtlas {
-                layout: atlas_handle.clone(),
-                index: initial_frame,
-            },
-            transform: enemy_transform,
-            ..Default::default()
-        };
+/// Spawns an enemy of the given type with its nameplate and health bar
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    enemy_type: EnemyType,
+    definition: &EnemyDefinition,
+    sheet: &EnemySheet,
+    coordinates: Vec3,
+    facing: Facing,
+    level: u32,
+) -> Entity {
+    let stats = &definition.stats;
+    // Health and damage scale with the levels above (or below) the catalog level
+    let scaling = 1.0 + LEVEL_SCALING * (level as f32 - stats.level as f32);
+    let scaling = scaling.max(MIN_LEVEL_SCALING);
+    let enemy_transform = Transform::from_translation(coordinates);
+    let tint = definition.sprite.color();
+    let sprite_bundle = SpriteSheetBundle {
+        sprite: Sprite {
+            color: tint,
+            custom_size: Some(Vec2::splat(TILE_SIZE * definition.sprite.scale)),
+            ..default()
+        },
+        texture: sheet.handle.clone(),
+        atlas: TextureAtlas {
+            layout: sheet.layout.clone(),
+            index: sheet.walk(facing)[0],
+        },
+        transform: enemy_transform,
+        ..Default::default()
+    };
 
-        let enemy_type = EnemyType::Skeleton;
-        let enemy = Enemy {
-            spawn_coords: coordinates,
-            display_name: enemy_type.display_name(),
-            ..Default::default()
-        };
+    let enemy = Enemy {
+        spawn_coords: coordinates,
+        display_name: definition.display_name.clone(),
+        speed: stats.speed,
+        level,
+        aggro_range: stats.aggro_range,
+        leash_range: stats.leash_range,
+        ..Default::default()
+    };
 
-        let offset_y = TILE_SIZE;
-        let ui_transform = Transform::from_translation(Vec3::new(0.0, offset_y / 1.5, 0.0));
+    let offset_y = TILE_SIZE;
+    let ui_transform = Transform::from_translation(Vec3::new(0.0, offset_y / 1.5, 0.0));
 
-        let name_ui = Text2dBundle {
-            text: Text::from_section(
-                enemy.display_name.clone(),
-                TextStyle {
-                    font_size: 15.0,
-                    color: Color::WHITE,
-                    ..default()
-                },
-            ),
-            transform: ui_transform,
-            ..default()
-        };
-        let health_ui = Transform::from_translation(Vec3::new(0.0, 32.0, 0.0));
-        let background_ui = SpriteBundle {
-            sprite: Sprite {
-                color: Color::BLACK,
-                custom_size: Some(Vec2::new(TILE_SIZE, 5.0)),
+    let name_ui = Text2dBundle {
+        text: Text::from_section(
+            enemy.display_name.clone(),
+            TextStyle {
+                font_size: 15.0,
+                color: Color::WHITE,
                 ..default()
             },
-            transform: health_ui,
+        ),
+        transform: ui_transform,
+        ..default()
+    };
+    let health_ui = Transform::from_translation(Vec3::new(0.0, 32.0, 0.0));
+    let background_ui = SpriteBundle {
+        sprite: Sprite {
+            color: Color::BLACK,
+            custom_size: Some(Vec2::new(TILE_SIZE, 5.0)),
             ..default()
-        };
+        },
+        transform: health_ui,
+        ..default()
+    };
 
-        let enemy_health = Health::new(130.0);
-        let enemy_health_percentage = enemy_health.current() / enemy_health.max();
-        let foreground_scale_x = TILE_SIZE * enemy_health_percentage;
-        let foreground_ui = SpriteBundle {
-            sprite: Sprite {
-                color: Color::RED,
-                custom_size: Some(Vec2::new(foreground_scale_x, 5.0)),
-                ..default()
-            },
-            transform: Transform::from_translation(Vec3::new(
-                -TILE_SIZE / 2.0 + foreground_scale_x / 2.0,
-                0.0,
-                0.0,
-            )),
+    let enemy_health = Health::new(stats.health * scaling);
+    let enemy_health_percentage = enemy_health.current() / enemy_health.max();
+    let foreground_scale_x = TILE_SIZE * enemy_health_percentage;
+    let foreground_ui = SpriteBundle {
+        sprite: Sprite {
+            color: Color::RED,
+            custom_size: Some(Vec2::new(foreground_scale_x, 5.0)),
             ..default()
-        };
-        let enemy = EnemyBundle {
-            enemy,
-            enemy_type,
-            facing,
-            health: Health::new(130.0),
-            sprite: sprite_bundle,
-            animation: FrameAnimation {
-                timer: Timer::from_seconds(0.1, TimerMode::Repeating),
-                frames: match facing {
-                    Facing::Up => enemy_up.to_vec(),
-                    Facing::Down => enemy_down.to_vec(),
-                    Facing::Left => enemy_left.to_vec(),
-                    Facing::Right => enemy_right.to_vec(),
-                },
-                current_frame: 0,
-            },
-            critical_hit: CriticalHit::new(0.1, 2.0),
-            damage: Damage::new(10.0),
-        };
-        commands
-            .spawn(enemy)
-            .insert(Name::new("enemy"))
+        },
+        transform: Transform::from_translation(Vec3::new(
+            -TILE_SIZE / 2.0 + foreground_scale_x / 2.0,
+            0.0,
+            0.0,
+        )),
+        ..default()
+    };
+    let enemy = EnemyBundle {
+        enemy,
+        enemy_type,
+        abilities: EnemyAbilities::new(definition.abilities.clone()),
+        facing,
+        health: enemy_health,
+        sprite: sprite_bundle,
+        tint: Tint(tint),
+        animation: FrameAnimation {
+            timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+            frames: sheet.walk(facing).to_vec(),
+            current_frame: 0,
+        },
+        critical_hit: CriticalHit::new(stats.crit_chance, stats.crit_multiplier),
+        damage: Damage::new(stats.damage * scaling),
+        threat: ThreatTable::default(),
+        ai: EnemyAi::from(&definition.ai),
+        path: PathFollower::default(),
+        velocity: Velocity::default(),
+        faction: definition.faction,
+        collider: Collider::capsule(
+            TILE_SIZE * 0.2 * definition.sprite.scale,
+            TILE_SIZE * 0.25 * definition.sprite.scale,
+        )
+        // Only detected by projectiles, melee reach is checked by the attacker
+        .with_layers(ENEMY_LAYER, 0),
+    };
+    let mut entity = commands.spawn(enemy);
+    entity.insert(Name::new("enemy")).with_children(|p| {
+        p.spawn((name_ui, EnemyNameUI));
+        p.spawn((background_ui, EnemyHealthBackgroundUI))
             .with_children(|p| {
-                p.spawn((name_ui, EnemyNameUI));
-                p.spawn((background_ui, EnemyHealthBackgroundUI))
-                    .with_children(|p| {
-                        p.spawn((foreground_ui, EnemyHealthForegroundUI));
-                    });
+                p.spawn((foreground_ui, EnemyHealthForegroundUI));
             });
+    });
+    if let Some(boss) = &definition.boss {
+        entity.insert(Boss::new(boss.clone()));
     }
+    entity.id()
 }
 
 #[derive(Event)]
-pub struct EnemyDefeatedEvent;
+pub struct EnemyDefeatedEvent {
+    pub entity: Entity,
+}
@@ -0,0 +1,150 @@
+use crate::abilities::AbilityKind;
+use crate::ai::RangedAttack;
+use crate::boss::BossProfile;
+use crate::enemy::EnemyType;
+use crate::faction::Faction;
+use crate::loot::LootItem;
+use crate::ron_asset::RonAssetLoader;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+const ENEMY_CATALOG_PATH: &str = "enemies.ron";
+
+pub struct EnemyCatalogPlugin;
+
+impl Plugin for EnemyCatalogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<EnemyCatalog>()
+            .register_asset_loader(RonAssetLoader::<EnemyCatalog>::new(&["enemies.ron"]))
+            .add_systems(Startup, load_enemy_catalog);
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug)]
+/// Definition of every enemy type, loaded from `assets/enemies.ron`
+pub struct EnemyCatalog {
+    pub enemies: HashMap<EnemyType, EnemyDefinition>,
+}
+
+impl EnemyCatalog {
+    pub fn get(&self, enemy_type: EnemyType) -> Option<&EnemyDefinition> {
+        self.enemies.get(&enemy_type)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EnemyDefinition {
+    pub display_name: String,
+    pub faction: Faction,
+    pub sprite: EnemySprite,
+    pub stats: EnemyStats,
+    pub ai: AiProfile,
+    /// Abilities used by the enemy, the first one being its basic attack
+    pub abilities: Vec<AbilityKind>,
+    #[serde(default)]
+    pub loot: Vec<LootEntry>,
+    /// Set for bosses, which are spawned once instead of randomly
+    #[serde(default)]
+    pub boss: Option<BossProfile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+/// LPC style spritesheet, walk rows are ordered up, left, down, right
+pub struct EnemySprite {
+    pub path: String,
+    pub frame_size: (f32, f32),
+    pub columns: usize,
+    pub rows: usize,
+    pub walk_row: usize,
+    pub walk_frames: usize,
+    pub death_row: usize,
+    pub death_frames: usize,
+    /// Color multiplied with the sprite, to tell apart types sharing a spritesheet
+    #[serde(default)]
+    pub tint: Option<(f32, f32, f32)>,
+    /// Displayed size relative to a tile
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl EnemySprite {
+    pub fn color(&self) -> Color {
+        self.tint
+            .map_or(Color::WHITE, |(r, g, b)| Color::rgb(r, g, b))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EnemyStats {
+    pub health: f32,
+    pub level: u32,
+    /// Movement speed in tiles per second
+    pub speed: f32,
+    pub aggro_range: f32,
+    pub leash_range: f32,
+    pub damage: f32,
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+/// Tuning of the `EnemyAi` state machine
+pub struct AiProfile {
+    pub wander_radius: f32,
+    pub attack_range: f32,
+    /// Time between two attacks, in seconds
+    pub attack_cooldown: f32,
+    /// Fraction of max health under which the enemy flees, 0 to never flee
+    pub flee_health: f32,
+    /// Ranged enemies keep their distance and shoot projectiles
+    #[serde(default)]
+    pub ranged: Option<RangedAttack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LootEntry {
+    pub item: LootItem,
+    /// Probability of dropping the item, between 0 and 1
+    pub chance: f32,
+}
+
+#[derive(Resource, Deref)]
+pub struct EnemyCatalogHandle(Handle<EnemyCatalog>);
+
+fn load_enemy_catalog(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyCatalogHandle(asset_server.load(ENEMY_CATALOG_PATH)));
+}
+
+/// Run condition, true once the enemy catalog is loaded
+pub fn catalog_loaded(
+    handle: Option<Res<EnemyCatalogHandle>>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+) -> bool {
+    handle.is_some_and(|handle| catalogs.contains(&**handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_defines_every_enemy_type() {
+        let catalog: EnemyCatalog =
+            ron::de::from_str(include_str!("../assets/enemies.ron")).unwrap();
+        for enemy_type in [
+            EnemyType::Skeleton,
+            EnemyType::SkeletonArcher,
+            EnemyType::Zombie,
+            EnemyType::Slime,
+            EnemyType::SkeletonLord,
+        ] {
+            let definition = catalog.get(enemy_type).unwrap();
+            assert!(!definition.abilities.is_empty());
+        }
+    }
+}
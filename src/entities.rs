@@ -21,6 +21,18 @@ pub enum Facing {
     Right,
 }
 
+impl Facing {
+    /// Returns the unit vector pointing in the facing direction
+    pub fn direction(&self) -> Vec2 {
+        match self {
+            Facing::Up => Vec2::Y,
+            Facing::Down => Vec2::NEG_Y,
+            Facing::Left => Vec2::NEG_X,
+            Facing::Right => Vec2::X,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct FrameAnimation {
     pub timer: Timer,
@@ -46,6 +58,14 @@ pub fn get_facing_direction(direction: Vec3) -> Facing {
 #[derive(Component, Debug, Deref, DerefMut)]
 pub struct DespawnTimer(pub Timer);
 
+#[derive(Component, Debug, Deref)]
+/// Base color of a sprite, restored after effects such as hit flashes
+pub struct Tint(pub Color);
+
+#[derive(Component, Debug)]
+/// Marks an entity whose health reached 0, it no longer takes damage nor acts
+pub struct Dead;
+
 #[derive(Component)]
 pub struct Health {
     current: f32,
@@ -63,7 +83,13 @@ impl Health {
         self.max
     }
     pub fn update(&mut self, damage: f32) {
-        self.current -= damage;
+        self.current = (self.current - damage).max(0.0);
+    }
+    /// Heals the entity up to its max health, returns the overheal
+    pub fn heal(&mut self, amount: f32) -> f32 {
+        let healed = self.current + amount;
+        self.current = healed.min(self.max);
+        (healed - self.max).max(0.0)
     }
 }
 
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Faction>()
+            .init_resource::<FactionRelations>();
+    }
+}
+
+#[derive(
+    Component, Reflect, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[reflect(Component)]
+/// Team an entity (or a projectile) belongs to
+pub enum Faction {
+    Player,
+    Undead,
+    Slime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+#[derive(Resource, Debug)]
+/// How factions behave toward each other, relations are symmetric.
+/// A faction is friendly with itself, pairs missing from the table are neutral
+pub struct FactionRelations {
+    relations: HashMap<(Faction, Faction), Relation>,
+}
+
+impl Default for FactionRelations {
+    fn default() -> Self {
+        let mut relations = Self {
+            relations: HashMap::new(),
+        };
+        relations.set(Faction::Player, Faction::Undead, Relation::Hostile);
+        relations.set(Faction::Player, Faction::Slime, Relation::Hostile);
+        relations
+    }
+}
+
+impl FactionRelations {
+    fn key(a: Faction, b: Faction) -> (Faction, Faction) {
+        (a.min(b), a.max(b))
+    }
+    pub fn set(&mut self, a: Faction, b: Faction, relation: Relation) {
+        self.relations.insert(Self::key(a, b), relation);
+    }
+    pub fn get(&self, a: Faction, b: Faction) -> Relation {
+        if a == b {
+            return Relation::Friendly;
+        }
+        self.relations
+            .get(&Self::key(a, b))
+            .copied()
+            .unwrap_or(Relation::Neutral)
+    }
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.get(a, b) == Relation::Hostile
+    }
+    pub fn is_friendly(&self, a: Faction, b: Faction) -> bool {
+        self.get(a, b) == Relation::Friendly
+    }
+}
@@ -0,0 +1,76 @@
+use crate::abilities::Projectile;
+use crate::collision::Collider;
+use crate::damage::Knockback;
+use crate::tilemap::TileMap;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+
+/// Time over which an entity arriving at a destination slows down, in seconds
+const ARRIVE_TIME: f32 = 0.1;
+
+pub struct KinematicsPlugin;
+
+impl Plugin for KinematicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Velocity>()
+            .register_type::<KinematicsSettings>()
+            .init_resource::<KinematicsSettings>()
+            .add_systems(
+                Update,
+                integrate_velocity
+                    .run_if(|settings: Res<KinematicsSettings>| !settings.fixed_timestep),
+            )
+            .add_systems(
+                FixedUpdate,
+                integrate_velocity
+                    .run_if(|settings: Res<KinematicsSettings>| settings.fixed_timestep),
+            );
+    }
+}
+
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct KinematicsSettings {
+    /// Integrates movement in `FixedUpdate` instead of once per frame
+    pub fixed_timestep: bool,
+}
+
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, Deref, DerefMut)]
+#[reflect(Component)]
+/// Velocity of an entity, in tiles per second
+pub struct Velocity(pub Vec2);
+
+/// Returns the velocity moving at `speed` (tiles per second) toward `destination`,
+/// slowing down when close so that the destination is never overshot
+pub fn arrive(position: Vec3, destination: Vec3, speed: f32) -> Vec2 {
+    let offset = (destination - position).truncate() / TILE_SIZE;
+    offset.normalize_or_zero() * speed.min(offset.length() / ARRIVE_TIME)
+}
+
+/// Moves every entity by its velocity, plus its knockback if it has been hit.
+/// Bodies with a collider slide along the tiles blocking movement, projectiles are handled on their own
+#[allow(clippy::type_complexity)]
+pub fn integrate_velocity(
+    mut query: Query<
+        (
+            &mut Transform,
+            Option<&Velocity>,
+            Option<&Knockback>,
+            Option<&Collider>,
+            Has<Projectile>,
+        ),
+        Or<(With<Velocity>, With<Knockback>)>,
+    >,
+    tilemap: Option<Res<TileMap>>,
+    time: Res<Time>,
+) {
+    for (mut transform, velocity, knockback, collider, is_projectile) in query.iter_mut() {
+        let velocity = velocity.map_or(Vec2::ZERO, |v| **v) + knockback.map_or(Vec2::ZERO, |k| **k);
+        let mut delta = velocity * TILE_SIZE * time.delta_seconds();
+        if let (Some(tilemap), Some(collider), false) = (&tilemap, collider, is_projectile) {
+            let center = transform.translation.truncate() + collider.offset;
+            delta = tilemap.slide(center, collider.half_extents(), delta);
+        }
+        transform.translation += delta.extend(0.0);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::enemy::{EnemyDefeatedEvent, EnemyType};
+use crate::enemy_catalog::{catalog_loaded, EnemyCatalog, EnemyCatalogHandle};
+use crate::entities::DespawnTimer;
+use crate::player::{Player, Potions};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+/// Distance under which the player picks up a drop
+const PICKUP_RANGE: f32 = 32.0;
+/// Time a drop stays on the ground, in seconds
+const LOOT_LIFETIME: f32 = 30.0;
+const LOOT_SIZE: f32 = 12.0;
+/// Maximum distance between a drop and the enemy that dropped it
+const LOOT_SPREAD: f32 = 16.0;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, drop_loot.run_if(catalog_loaded))
+            .add_systems(Update, pickup_loot);
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LootItem {
+    HealthPotion,
+}
+
+impl LootItem {
+    fn color(&self) -> Color {
+        match self {
+            LootItem::HealthPotion => Color::rgb(0.9, 0.1, 0.2),
+        }
+    }
+}
+
+#[derive(Component, Deref)]
+/// Item lying on the ground, waiting to be picked up
+pub struct LootDrop(LootItem);
+
+/// Rolls the loot table of defeated enemies
+fn drop_loot(
+    mut commands: Commands,
+    mut events: EventReader<EnemyDefeatedEvent>,
+    q_enemies: Query<(&EnemyType, &Transform)>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+) {
+    let Some(catalog) = catalogs.get(&**catalog) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    for event in events.read() {
+        let Ok((enemy_type, transform)) = q_enemies.get(event.entity) else {
+            continue;
+        };
+        let Some(definition) = catalog.get(*enemy_type) else {
+            continue;
+        };
+        for entry in definition.loot.iter() {
+            if !rng.gen_bool(entry.chance.clamp(0.0, 1.0) as f64) {
+                continue;
+            }
+            let offset = Vec2::new(
+                rng.gen_range(-LOOT_SPREAD..LOOT_SPREAD),
+                rng.gen_range(-LOOT_SPREAD..LOOT_SPREAD),
+            );
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: entry.item.color(),
+                        custom_size: Some(Vec2::splat(LOOT_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        transform.translation.truncate().extend(0.5) + offset.extend(0.0),
+                    ),
+                    ..default()
+                },
+                LootDrop(entry.item),
+                DespawnTimer(Timer::from_seconds(LOOT_LIFETIME, TimerMode::Once)),
+                Name::new("loot"),
+            ));
+        }
+    }
+}
+
+fn pickup_loot(
+    mut commands: Commands,
+    mut q_players: Query<(&Transform, &mut Potions), With<Player>>,
+    q_drops: Query<(Entity, &Transform, &LootDrop)>,
+) {
+    for (player_transform, mut potions) in q_players.iter_mut() {
+        let position = player_transform.translation.truncate();
+        for (entity, transform, drop) in q_drops.iter() {
+            if transform.translation.truncate().distance(position) > PICKUP_RANGE {
+                continue;
+            }
+            match **drop {
+                LootItem::HealthPotion => **potions += 1,
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -1,8 +1,28 @@
 use crate::abilities::AbilityPlugin;
+use crate::ai::AiPlugin;
+use crate::boss::BossPlugin;
+use crate::chunks::ChunkPlugin;
+use crate::collision::CollisionPlugin;
 use crate::damage::DamagePlugin;
+use crate::damage_numbers::DamageNumbersPlugin;
+use crate::dungeon::DungeonPlugin;
+use crate::effects::EffectsPlugin;
+use crate::elite::ElitePlugin;
 use crate::enemy::EnemyPlugin;
+use crate::enemy_catalog::EnemyCatalogPlugin;
+use crate::faction::FactionPlugin;
+use crate::kinematics::KinematicsPlugin;
+use crate::loot::LootPlugin;
+use crate::meter::DamageMeterPlugin;
 use crate::player::PlayerPlugin;
+use crate::respawn::RespawnPlugin;
+use crate::spatial_grid::SpatialGridPlugin;
+use crate::spawner::SpawnerPlugin;
 use crate::splash::SplashPlugin;
+use crate::steering::SteeringPlugin;
+use crate::threat::ThreatPlugin;
+use crate::tiled::TiledPlugin;
+use crate::tilemap::TilemapPlugin;
 use crate::world::WorldPlugin;
 use bevy::prelude::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
@@ -10,11 +30,34 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use self::exp::ExperiencePlugin;
 
 pub mod abilities;
+pub mod ai;
+pub mod boss;
+pub mod chunks;
+pub mod collision;
 pub mod damage;
+pub mod damage_numbers;
+pub mod dungeon;
+pub mod effects;
+pub mod elite;
 pub mod enemy;
+pub mod enemy_catalog;
 pub mod entities;
 pub mod exp;
+pub mod faction;
+pub mod kinematics;
+pub mod loot;
+pub mod meter;
+pub mod pathfinding;
 pub mod player;
+pub mod respawn;
+pub mod ron_asset;
+pub mod spatial;
+pub mod spatial_grid;
+pub mod spawner;
+pub mod steering;
+pub mod threat;
+pub mod tiled;
+pub mod tilemap;
 pub mod world;
 
 pub const TILE_SIZE: f32 = 64.0;
@@ -32,10 +75,30 @@ struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(PlayerPlugin)
+            .add_plugins(FactionPlugin)
+            .add_plugins(TilemapPlugin)
+            .add_plugins(TiledPlugin)
+            .add_plugins(DungeonPlugin)
+            .add_plugins(ChunkPlugin)
+            .add_plugins(KinematicsPlugin)
+            .add_plugins(SpatialGridPlugin)
+            .add_plugins(CollisionPlugin)
             .add_plugins(DamagePlugin)
+            .add_plugins(DamageNumbersPlugin)
+            .add_plugins(EffectsPlugin)
+            .add_plugins(EnemyCatalogPlugin)
             .add_plugins(EnemyPlugin)
+            .add_plugins(SpawnerPlugin)
+            .add_plugins(RespawnPlugin)
+            .add_plugins(ThreatPlugin)
+            .add_plugins(AiPlugin)
+            .add_plugins(SteeringPlugin)
+            .add_plugins(ElitePlugin)
+            .add_plugins(BossPlugin)
             .add_plugins(ExperiencePlugin)
+            .add_plugins(LootPlugin)
             .add_plugins(AbilityPlugin)
+            .add_plugins(DamageMeterPlugin)
             .add_plugins(WorldPlugin)
             .add_plugins(WorldInspectorPlugin::new());
     }
@@ -0,0 +1,213 @@
+use crate::abilities::AbilityKind;
+use crate::damage::DamageDealtEvent;
+use crate::player::Player;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::collections::VecDeque;
+
+/// Length of the sliding window used for the live DPS value, in seconds
+const DPS_WINDOW: f32 = 5.0;
+/// Time without dealing damage after which the current fight is considered over
+const FIGHT_TIMEOUT: f32 = 5.0;
+const METER_TOGGLE_KEY: KeyCode = KeyCode::KeyM;
+
+pub struct DamageMeterPlugin;
+
+impl Plugin for DamageMeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DamageMeter>()
+            .add_systems(Startup, setup_meter_ui)
+            .add_systems(Update, record_damage)
+            .add_systems(Update, toggle_meter)
+            .add_systems(Update, update_meter_ui);
+    }
+}
+
+#[derive(Component)]
+pub struct DamageMeterUI;
+
+#[derive(Default, Debug, Clone)]
+pub struct AbilityStats {
+    pub damage: f32,
+    pub hits: u32,
+    pub crits: u32,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Damage dealt over a period of time (a single fight or the whole session)
+pub struct DamageStats {
+    pub total: f32,
+    pub hits: u32,
+    pub crits: u32,
+    pub started_at: Option<f32>,
+    pub last_hit_at: f32,
+    pub per_ability: HashMap<AbilityKind, AbilityStats>,
+}
+
+impl DamageStats {
+    pub fn record(&mut self, ability: AbilityKind, damage: f32, is_crit: bool, now: f32) {
+        self.started_at.get_or_insert(now);
+        self.last_hit_at = now;
+        self.total += damage;
+        self.hits += 1;
+        let ability_stats = self.per_ability.entry(ability).or_default();
+        ability_stats.damage += damage;
+        ability_stats.hits += 1;
+        if is_crit {
+            self.crits += 1;
+            ability_stats.crits += 1;
+        }
+    }
+    /// Elapsed time since the first hit, in seconds
+    pub fn duration(&self, now: f32) -> f32 {
+        self.started_at.map_or(0.0, |start| now - start)
+    }
+    /// Average damage per second since the first hit
+    pub fn dps(&self, now: f32) -> f32 {
+        // Avoid huge values right after the first hit
+        self.total / self.duration(now).max(1.0)
+    }
+    pub fn crit_rate(&self) -> f32 {
+        if self.hits == 0 {
+            0.0
+        } else {
+            self.crits as f32 / self.hits as f32
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+/// Tracks the damage dealt by the player
+pub struct DamageMeter {
+    pub visible: bool,
+    pub fight: DamageStats,
+    pub session: DamageStats,
+    /// Recent hits as (time, damage), used for the sliding window DPS
+    window: VecDeque<(f32, f32)>,
+}
+
+impl DamageMeter {
+    pub fn record(&mut self, ability: AbilityKind, damage: f32, is_crit: bool, now: f32) {
+        if self.fight.started_at.is_none() || now - self.fight.last_hit_at > FIGHT_TIMEOUT {
+            self.fight = DamageStats::default();
+        }
+        self.fight.record(ability, damage, is_crit, now);
+        self.session.record(ability, damage, is_crit, now);
+        self.window.push_back((now, damage));
+        self.prune_window(now);
+    }
+    pub fn in_fight(&self, now: f32) -> bool {
+        self.fight.started_at.is_some() && now - self.fight.last_hit_at <= FIGHT_TIMEOUT
+    }
+    /// Damage per second over the last `DPS_WINDOW` seconds
+    pub fn window_dps(&mut self, now: f32) -> f32 {
+        self.prune_window(now);
+        self.window.iter().map(|(_, damage)| damage).sum::<f32>() / DPS_WINDOW
+    }
+    fn prune_window(&mut self, now: f32) {
+        while self
+            .window
+            .front()
+            .is_some_and(|(time, _)| now - time > DPS_WINDOW)
+        {
+            self.window.pop_front();
+        }
+    }
+}
+
+fn record_damage(
+    mut events: EventReader<DamageDealtEvent>,
+    mut meter: ResMut<DamageMeter>,
+    q_player: Query<(), With<Player>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for event in events.read() {
+        if event.source.is_some_and(|source| q_player.contains(source)) {
+            meter.record(event.ability, event.damage, event.is_crit, now);
+        }
+    }
+}
+
+fn toggle_meter(keyboard: Res<ButtonInput<KeyCode>>, mut meter: ResMut<DamageMeter>) {
+    if keyboard.just_pressed(METER_TOGGLE_KEY) {
+        meter.visible = !meter.visible;
+    }
+}
+
+fn setup_meter_ui(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 16.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            display: Display::None,
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        DamageMeterUI,
+        Name::new("damage meter"),
+    ));
+}
+
+fn format_stats(label: &str, stats: &DamageStats, now: f32) -> String {
+    let mut text = format!(
+        "{label} ({:.1}s)\n  total {:.0}  dps {:.1}  crit {:.0}%\n",
+        stats.duration(now),
+        stats.total,
+        stats.dps(now),
+        stats.crit_rate() * 100.0
+    );
+    let mut abilities = stats.per_ability.iter().collect::<Vec<_>>();
+    abilities.sort_by(|a, b| b.1.damage.total_cmp(&a.1.damage));
+    for (ability, ability_stats) in abilities {
+        let share = if stats.total > 0.0 {
+            ability_stats.damage / stats.total * 100.0
+        } else {
+            0.0
+        };
+        text.push_str(&format!(
+            "  {}: {:.0} ({:.0}%) {} hits, {} crits\n",
+            ability.display_name(),
+            ability_stats.damage,
+            share,
+            ability_stats.hits,
+            ability_stats.crits
+        ));
+    }
+    text
+}
+
+fn update_meter_ui(
+    mut meter: ResMut<DamageMeter>,
+    mut q_ui: Query<(&mut Text, &mut Style), With<DamageMeterUI>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    let (mut text, mut style) = q_ui.single_mut();
+    if !meter.visible {
+        style.display = Display::None;
+        return;
+    }
+    style.display = Display::Flex;
+    // A finished fight is frozen at its last hit
+    let (fight_label, fight_end) = if meter.in_fight(now) {
+        ("Fight", now)
+    } else {
+        ("Last fight", meter.fight.last_hit_at)
+    };
+    text.sections[0].value = format!(
+        "Damage meter [M]\nDPS ({DPS_WINDOW:.0}s): {:.1}\n{}{}",
+        meter.window_dps(now),
+        format_stats(fight_label, &meter.fight, fight_end),
+        format_stats("Session", &meter.session, now)
+    );
+}
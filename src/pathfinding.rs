@@ -0,0 +1,259 @@
+use crate::tilemap::TileMap;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::time::Duration;
+
+/// Cost of a straight step between two tiles
+const STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal step, about `STRAIGHT_COST` * sqrt(2)
+const DIAGONAL_COST: u32 = 14;
+/// Tiles expanded before giving up on a search. Everything outside the map is open floor,
+/// so a search for an unreachable tile would never end otherwise
+const MAX_SEARCHED_TILES: usize = 4096;
+/// Minimum time between two path searches of the same walker, in seconds
+const REPATH_INTERVAL: f32 = 0.5;
+/// Distance under which a waypoint is considered reached, in pixels
+const WAYPOINT_REACHED_DISTANCE: f32 = TILE_SIZE * 0.25;
+/// Half width of the corridor checked for a straight walk, roughly the width of an enemy
+const CLEARANCE: f32 = TILE_SIZE * 0.3;
+/// Distance between two points checked along a straight walk, in pixels
+const SIGHT_STEP: f32 = TILE_SIZE * 0.25;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// Octile distance between two tiles, never more than the cost of the shortest path
+fn heuristic(from: IVec2, to: IVec2) -> u32 {
+    let offset = (to - from).abs();
+    let (diagonal, straight) = (
+        offset.min_element(),
+        offset.max_element() - offset.min_element(),
+    );
+    DIAGONAL_COST * diagonal as u32 + STRAIGHT_COST * straight as u32
+}
+
+/// Shortest walkable path from `from` to `to` with A*, both included. Diagonal steps never cut
+/// the corner of a tile blocking movement. `None` when `to` is blocked or too far to be found
+pub fn find_path(map: &TileMap, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+    if map.get(to).blocks_movement() {
+        return None;
+    }
+    let blocked = |tile: IVec2| map.get(tile).blocks_movement();
+    // Cost of the cheapest known way to every reached tile, and the tile it comes from
+    let mut reached = HashMap::default();
+    reached.insert(from, (0, from));
+    let mut open = BinaryHeap::from([Reverse((heuristic(from, to), 0, from.to_array()))]);
+    let mut searched = 0;
+    while let Some(Reverse((_, cost, tile))) = open.pop() {
+        let tile = IVec2::from_array(tile);
+        if tile == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while current != from {
+                current = reached[&current].1;
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // Queued again since, with a lower cost
+        if reached.get(&tile).is_some_and(|(best, _)| *best < cost) {
+            continue;
+        }
+        searched += 1;
+        if searched > MAX_SEARCHED_TILES {
+            return None;
+        }
+        for step in NEIGHBOURS {
+            let next = tile + step;
+            let diagonal = step.x != 0 && step.y != 0;
+            let cuts_corner =
+                diagonal && (blocked(tile + step * IVec2::X) || blocked(tile + step * IVec2::Y));
+            if blocked(next) || cuts_corner {
+                continue;
+            }
+            let step_cost = if diagonal {
+                DIAGONAL_COST
+            } else {
+                STRAIGHT_COST
+            };
+            let next_cost = cost + step_cost;
+            if reached
+                .get(&next)
+                .is_some_and(|(best, _)| *best <= next_cost)
+            {
+                continue;
+            }
+            reached.insert(next, (next_cost, tile));
+            open.push(Reverse((
+                next_cost + heuristic(next, to),
+                next_cost,
+                next.to_array(),
+            )));
+        }
+    }
+    None
+}
+
+/// Whether a body can walk in a straight line from `from` to `to` without hitting a tile
+/// blocking movement. The center line and both edges of a `CLEARANCE` wide corridor are checked
+pub fn can_walk_straight(map: &TileMap, from: Vec2, to: Vec2) -> bool {
+    let steps = (from.distance(to) / SIGHT_STEP).ceil().max(1.0);
+    let side = (to - from).normalize_or_zero().perp() * CLEARANCE;
+    [Vec2::ZERO, side, -side].into_iter().all(|side| {
+        (0..=steps as u32).all(|step| {
+            let point = from.lerp(to, step as f32 / steps) + side;
+            !map.blocks_movement_at(point)
+        })
+    })
+}
+
+#[derive(Component, Debug)]
+/// Path a walker follows around the terrain. It is kept while its goal tile does not change,
+/// and searched again at most every `REPATH_INTERVAL` seconds
+pub struct PathFollower {
+    /// Tile the current path leads to
+    goal: Option<IVec2>,
+    /// Centers of the tiles left to walk through, the next one first
+    waypoints: VecDeque<Vec2>,
+    repath_timer: Timer,
+}
+
+impl Default for PathFollower {
+    fn default() -> Self {
+        let mut repath_timer = Timer::from_seconds(REPATH_INTERVAL, TimerMode::Once);
+        // The first search is not delayed
+        repath_timer.tick(Duration::from_secs_f32(REPATH_INTERVAL));
+        Self {
+            goal: None,
+            waypoints: VecDeque::new(),
+            repath_timer,
+        }
+    }
+}
+
+impl PathFollower {
+    pub fn tick(&mut self, delta: Duration) {
+        self.repath_timer.tick(delta);
+    }
+    /// Point to walk toward on the way to `destination`: the destination itself when it can be
+    /// walked to straight, otherwise the farthest waypoint of the path that can
+    pub fn next_waypoint(&mut self, map: &TileMap, position: Vec2, destination: Vec2) -> Vec2 {
+        if can_walk_straight(map, position, destination) {
+            self.goal = None;
+            self.waypoints.clear();
+            return destination;
+        }
+        let goal = map.tile_at(destination);
+        let stale = self.goal != Some(goal) || self.waypoints.is_empty();
+        if stale && self.repath_timer.finished() {
+            self.repath_timer.reset();
+            self.goal = Some(goal);
+            self.waypoints = find_path(map, map.tile_at(position), goal)
+                .map(|path| {
+                    // The first tile is the one the walker stands on
+                    path.into_iter()
+                        .skip(1)
+                        .map(|tile| map.tile_center(tile))
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+        while self.waypoints.len() > 1 && can_walk_straight(map, position, self.waypoints[1]) {
+            self.waypoints.pop_front();
+        }
+        if self
+            .waypoints
+            .front()
+            .is_some_and(|waypoint| waypoint.distance(position) <= WAYPOINT_REACHED_DISTANCE)
+        {
+            self.waypoints.pop_front();
+        }
+        // Without a path, walking straight at least slides along the obstacle
+        self.waypoints.front().copied().unwrap_or(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_go_around_walls() {
+        let map = TileMap::from_ascii(&[
+            "#######", //
+            "#.....#", //
+            "#.###.#", //
+            "#...#.#", //
+            "#######",
+        ]);
+        let path = find_path(&map, IVec2::new(1, 1), IVec2::new(3, 1)).unwrap();
+        assert_eq!(path.first(), Some(&IVec2::new(1, 1)));
+        assert_eq!(path.last(), Some(&IVec2::new(3, 1)));
+        assert!(path.iter().all(|tile| !map.get(*tile).blocks_movement()));
+        assert_eq!(path.len(), 3);
+
+        // The wall in the middle has to be walked around, through the top row
+        let path = find_path(&map, IVec2::new(1, 1), IVec2::new(5, 1)).unwrap();
+        assert!(path.iter().all(|tile| !map.get(*tile).blocks_movement()));
+        assert!(path.contains(&IVec2::new(3, 3)));
+        for pair in path.windows(2) {
+            assert_eq!((pair[1] - pair[0]).abs().max_element(), 1);
+        }
+    }
+
+    #[test]
+    fn corners_are_not_cut() {
+        let map = TileMap::from_ascii(&[
+            "...", //
+            ".#.", //
+            "...",
+        ]);
+        let path = find_path(&map, IVec2::new(0, 1), IVec2::new(1, 2)).unwrap();
+        assert_eq!(
+            path,
+            vec![IVec2::new(0, 1), IVec2::new(0, 2), IVec2::new(1, 2)]
+        );
+    }
+
+    #[test]
+    fn enclosed_tiles_are_unreachable() {
+        let map = TileMap::from_ascii(&[
+            "###", //
+            "#.#", //
+            "###",
+        ]);
+        assert_eq!(find_path(&map, IVec2::new(-2, -2), IVec2::new(1, 1)), None);
+        assert_eq!(find_path(&map, IVec2::new(-2, -2), IVec2::new(0, 0)), None);
+    }
+
+    #[test]
+    fn followers_walk_straight_when_nothing_is_in_the_way() {
+        let map = TileMap::from_ascii(&[
+            "#.....", //
+            "#.##..", //
+            "#.....",
+        ]);
+        let mut follower = PathFollower::default();
+        let start = map.tile_center(IVec2::new(1, 0));
+        let open = map.tile_center(IVec2::new(5, 0));
+        assert_eq!(follower.next_waypoint(&map, start, open), open);
+
+        // Behind the wall, the follower heads for a tile it can walk straight to instead
+        let behind = map.tile_center(IVec2::new(3, 2));
+        let waypoint = follower.next_waypoint(&map, start, behind);
+        assert_ne!(waypoint, behind);
+        assert!(can_walk_straight(&map, start, waypoint));
+    }
+}
@@ -1,7 +1,18 @@
-use crate::abilities::{AbilitySheet, Fireball, Projectile, FIREBALL_BASE_DAMAGE};
-use crate::damage::{CriticalHit, Damage};
-use crate::entities::{DespawnTimer, Facing, FrameAnimation, Health, SpriteSheet};
+use crate::abilities::{
+    projectile_collider, AbilityKind, AbilitySheet, Fireball, Projectile, FIREBALL_BASE_DAMAGE,
+};
+use crate::collision::{Collider, PLAYER_LAYER};
+use crate::damage::{
+    CriticalHit, Damage, HealEvent, InvulnerabilityFrames, Invulnerable, Lifesteal,
+};
+use crate::effects::{ApplyEffectEvent, EffectKind, OnHitEffect, PeriodicEffect, TickAmount};
+use crate::entities::{
+    Dead, DespawnTimer, Facing, FrameAnimation, Health, HealthUpdateEvent, SpriteSheet,
+};
 use crate::exp::{Experience, Level};
+use crate::faction::Faction;
+use crate::kinematics::Velocity;
+use crate::tiled::{CurrentMap, TiledMap};
 use crate::TILE_SIZE;
 use bevy::prelude::*;
 
@@ -9,7 +20,7 @@ use bevy::prelude::*;
 const PLAYER_FRAMES: usize = 9;
 /// Player sprite cast animation frames
 const PLAYER_CAST_FRAMES: usize = 7;
-/// Player base movement speed
+/// Player base movement speed, in tiles per second
 const PLAYER_SPEED: f32 = 2.0;
 const ANIMATION_WALKING_SPEED: f32 = 0.1;
 const ANIMATION_CASTING_SPEED: f32 = 0.05;
@@ -21,6 +32,30 @@ const ROWS: usize = 21;
 const ENERGY_RECOVERY: f32 = 15.0; // per second
 const ENERGY_COST: f32 = 10.0; // per second
 
+/// Fraction of the fireball damage dealt by each burn tick
+const FIREBALL_BURN_RATIO: f32 = 0.1;
+/// Fireball burn duration in seconds, ticking every second
+const FIREBALL_BURN_DURATION: f32 = 3.0;
+/// Fraction of the player's max health restored by Mend
+const MEND_HEAL_RATIO: f32 = 0.2;
+/// Fraction of the player's max health restored every second by Mend's regeneration
+const MEND_REGEN_RATIO: f32 = 0.02;
+/// Mend regeneration duration in seconds
+const MEND_REGEN_DURATION: f32 = 5.0;
+const MEND_ENERGY_COST: f32 = 30.0;
+/// Mend cooldown in seconds
+const MEND_COOLDOWN: f32 = 5.0;
+const HEALTH_POTION_HEAL: f32 = 150.0;
+const STARTING_POTIONS: u32 = 3;
+/// Fraction of the damage dealt by the player returned as healing
+const PLAYER_LIFESTEAL: f32 = 0.05;
+/// Invulnerability after being hit, in seconds
+const PLAYER_I_FRAMES: f32 = 0.8;
+/// Invulnerability right after spawning, in seconds
+const SPAWN_PROTECTION: f32 = 2.0;
+/// Time between the player's death and its respawn, in seconds
+const RESPAWN_DELAY: f32 = 3.0;
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub player: Player,
@@ -33,6 +68,13 @@ pub struct PlayerBundle {
     pub level: Level,
     pub xp: Experience,
     pub state: PlayerState,
+    pub lifesteal: Lifesteal,
+    pub potions: Potions,
+    pub mend_cooldown: MendCooldown,
+    pub i_frames: InvulnerabilityFrames,
+    pub velocity: Velocity,
+    pub faction: Faction,
+    pub collider: Collider,
 }
 
 #[derive(Component, Default, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
@@ -59,6 +101,29 @@ impl Default for Player {
     }
 }
 
+#[derive(Component, Reflect, Deref, DerefMut)]
+#[reflect(Component)]
+/// Number of health potions the player carries
+pub struct Potions(u32);
+
+impl Potions {
+    pub fn new(count: u32) -> Self {
+        Self(count)
+    }
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct MendCooldown(Timer);
+
+impl Default for MendCooldown {
+    fn default() -> Self {
+        // Mend is available as soon as the player spawns
+        let mut timer = Timer::from_seconds(MEND_COOLDOWN, TimerMode::Once);
+        timer.tick(timer.duration());
+        Self(timer)
+    }
+}
+
 #[derive(Component)]
 pub struct LevelDisplay;
 
@@ -105,6 +170,37 @@ fn setup_energy_ui(mut commands: Commands, _asset_server: Res<AssetServer>) {
         EnergyDisplay,
     ));
 }
+#[derive(Component)]
+pub struct PotionDisplay;
+
+fn setup_potion_ui(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            format!("Potions: {STARTING_POTIONS}"),
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            align_self: AlignSelf::FlexEnd,
+            position_type: PositionType::Absolute,
+            top: Val::Px(70.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        PotionDisplay,
+    ));
+}
+fn update_potion_ui(
+    mut ui_query: Query<&mut Text, With<PotionDisplay>>,
+    player_query: Query<&Potions, With<Player>>,
+) {
+    let potions = player_query.single();
+    let mut potion_text = ui_query.single_mut();
+    potion_text.sections[0].value = format!("Potions: {}", **potions);
+}
 fn update_energy_ui(
     mut ui_query: Query<&mut Text, With<EnergyDisplay>>,
     player_query: Query<&Player>,
@@ -120,29 +216,97 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Player>()
             .register_type::<PlayerState>()
+            .register_type::<Potions>()
             .add_systems(Startup, spawn_player)
             .add_systems(Startup, setup_energy_ui)
             .add_systems(Startup, setup_level_ui)
+            .add_systems(Startup, setup_potion_ui)
             .add_systems(Update, player_mouvement)
             .add_systems(Update, animate_player)
             .add_systems(Update, update_player_graphics)
             .add_systems(Update, energy_system)
             .add_systems(Update, update_energy_ui)
-            .add_systems(Update, throw_fireball);
+            .add_systems(Update, update_potion_ui)
+            .add_systems(Update, throw_fireball)
+            .add_systems(Update, cast_mend)
+            .add_systems(Update, drink_potion)
+            .add_systems(Update, (player_death, respawn_player).chain());
     }
 }
 
+#[derive(Component, Deref, DerefMut)]
+/// Running while the player is dead
+pub struct RespawnTimer(Timer);
+
+#[allow(clippy::type_complexity)]
+fn player_death(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Velocity, &mut PlayerState), (With<Player>, Added<Dead>)>,
+) {
+    for (entity, mut velocity, mut state) in q_player.iter_mut() {
+        **velocity = Vec2::ZERO;
+        *state = PlayerState::Idle;
+        commands
+            .entity(entity)
+            .insert(RespawnTimer(Timer::from_seconds(
+                RESPAWN_DELAY,
+                TimerMode::Once,
+            )));
+    }
+}
+
+/// Brings the player back at the spawn of the current map with full health
+fn respawn_player(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Transform, &mut Health, &mut RespawnTimer), With<Player>>,
+    current: Option<Res<CurrentMap>>,
+    maps: Res<Assets<TiledMap>>,
+    mut health_events: EventWriter<HealthUpdateEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut health, mut timer) in q_player.iter_mut() {
+        if !timer.tick(time.delta()).finished() {
+            continue;
+        }
+        if let Some(spawn) = current.as_ref().and_then(|map| map.player_spawn(&maps)) {
+            transform.translation = spawn.extend(transform.translation.z);
+        }
+        *health = Health::new(health.max());
+        health_events.send(HealthUpdateEvent {
+            entity,
+            total_health: health.max(),
+            new_health: health.current(),
+        });
+        commands
+            .entity(entity)
+            .remove::<(Dead, RespawnTimer)>()
+            .insert(Invulnerable::new(SPAWN_PROTECTION));
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn throw_fireball(
     mut commands: Commands,
     keyboard: Res<ButtonInput<MouseButton>>,
-    mut player_query: Query<(&Facing, &mut PlayerState, &Level, &Transform), With<Player>>,
+    mut player_query: Query<
+        (Entity, &Facing, &mut PlayerState, &Level, &Transform),
+        (With<Player>, Without<Dead>),
+    >,
     abilities: Res<AbilitySheet>,
 ) {
     if keyboard.just_pressed(MouseButton::Right) {
-        let (facing, mut player_state, level, transform) = player_query.single_mut();
+        let Ok((player_entity, facing, mut player_state, level, transform)) =
+            player_query.get_single_mut()
+        else {
+            return;
+        };
         *player_state = PlayerState::Casting;
         let direction = facing;
-        let projectile = Projectile::default();
+        let projectile = Projectile {
+            owner: Some(player_entity),
+            ..default()
+        };
+        let velocity = Velocity(direction.direction() * projectile.speed);
         let player_coords = transform.translation;
         let initial_frame = match direction {
             Facing::Up => abilities.fireball.up[0],
@@ -177,16 +341,104 @@ fn throw_fireball(
                     },
                     current_frame: 0,
                 },
+                velocity,
+                Faction::Player,
                 Fireball,
+                AbilityKind::Fireball,
+                projectile_collider(),
                 DespawnTimer(Timer::from_seconds(5.0, TimerMode::Once)),
                 Damage::new(fireball_dmg),
                 // 10% chance to deal double damage
                 CriticalHit::new(0.1, 2.0),
+                // Burn damage is snapshotted from the fireball damage at cast time
+                OnHitEffect(
+                    PeriodicEffect::new(
+                        EffectKind::Damage,
+                        AbilityKind::Burn,
+                        TickAmount::Snapshot(fireball_dmg * FIREBALL_BURN_RATIO),
+                        1.0,
+                        FIREBALL_BURN_DURATION,
+                    )
+                    .with_source(player_entity),
+                ),
                 *facing,
             ))
             .insert(Name::new("fireball"));
     }
 }
+#[allow(clippy::type_complexity)]
+fn cast_mend(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Player,
+            &mut PlayerState,
+            &mut MendCooldown,
+            &Health,
+        ),
+        (With<Player>, Without<Dead>),
+    >,
+    mut heal_events: EventWriter<HealEvent>,
+    mut effect_events: EventWriter<ApplyEffectEvent>,
+    time: Res<Time>,
+) {
+    let Ok((player_entity, mut player, mut player_state, mut cooldown, health)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+    cooldown.tick(time.delta());
+    if !keyboard.just_pressed(KeyCode::KeyQ)
+        || !cooldown.finished()
+        || player.energy < MEND_ENERGY_COST
+    {
+        return;
+    }
+    player.energy -= MEND_ENERGY_COST;
+    cooldown.reset();
+    *player_state = PlayerState::Casting;
+    heal_events.send(HealEvent {
+        amount: health.max() * MEND_HEAL_RATIO,
+        entity: player_entity,
+        source: Some(player_entity),
+        ability: AbilityKind::Mend,
+    });
+    effect_events.send(ApplyEffectEvent {
+        target: player_entity,
+        effect: PeriodicEffect::new(
+            EffectKind::Heal,
+            AbilityKind::Regeneration,
+            TickAmount::Snapshot(health.max() * MEND_REGEN_RATIO),
+            1.0,
+            MEND_REGEN_DURATION,
+        )
+        .with_source(player_entity),
+    });
+}
+#[allow(clippy::type_complexity)]
+fn drink_potion(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut player_query: Query<(Entity, &mut Potions), (With<Player>, Without<Dead>)>,
+    mut heal_events: EventWriter<HealEvent>,
+) {
+    if !keyboard.just_pressed(KeyCode::Digit1) {
+        return;
+    }
+    let Ok((player_entity, mut potions)) = player_query.get_single_mut() else {
+        return;
+    };
+    if **potions == 0 {
+        return;
+    }
+    **potions -= 1;
+    heal_events.send(HealEvent {
+        amount: HEALTH_POTION_HEAL,
+        entity: player_entity,
+        source: Some(player_entity),
+        ability: AbilityKind::HealthPotion,
+    });
+}
 fn energy_system(
     mut player_query: Query<(&mut PlayerState, &mut Player)>,
     keyboard: Res<ButtonInput<KeyCode>>,
@@ -265,14 +517,17 @@ fn animate_player(
 }
 
 fn player_mouvement(
-    mut player_query: Query<(&mut PlayerState, &Player, &mut Facing, &mut Transform)>,
+    mut player_query: Query<(&mut PlayerState, &Player, &mut Facing, &mut Velocity), Without<Dead>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
 ) {
-    let (mut player_state, player, mut facing, mut transform) = player_query.single_mut();
+    let Ok((mut player_state, player, mut facing, mut velocity)) = player_query.get_single_mut()
+    else {
+        return;
+    };
 
     // TODO: remove?
     if PlayerState::Casting == *player_state {
+        **velocity = Vec2::ZERO;
         return;
     }
     *player_state = PlayerState::Idle;
@@ -283,42 +538,39 @@ fn player_mouvement(
         1.0
     };
 
-    let mut y_delta = 0.0;
+    let mut y_velocity = 0.0;
     if keyboard_input.pressed(KeyCode::KeyW) {
-        y_delta += player.speed * TILE_SIZE * time.delta_seconds() * speed_modif;
+        y_velocity += player.speed * speed_modif;
     }
     if keyboard_input.pressed(KeyCode::KeyS) {
-        y_delta -= player.speed * TILE_SIZE * time.delta_seconds() * speed_modif;
+        y_velocity -= player.speed * speed_modif;
     }
-    let target = transform.translation + Vec3::new(0.0, y_delta, 0.0);
 
-    if y_delta != 0.0 {
+    if y_velocity != 0.0 {
         *player_state = PlayerState::Moving;
-        if y_delta > 0.0 {
+        if y_velocity > 0.0 {
             *facing = Facing::Up;
-        } else if y_delta < 0.0 {
+        } else if y_velocity < 0.0 {
             *facing = Facing::Down;
         }
-        transform.translation = target;
     }
-    let mut x_delta = 0.0;
+    let mut x_velocity = 0.0;
     if keyboard_input.pressed(KeyCode::KeyA) {
-        x_delta -= player.speed * TILE_SIZE * time.delta_seconds() * speed_modif;
+        x_velocity -= player.speed * speed_modif;
     }
     if keyboard_input.pressed(KeyCode::KeyD) {
-        x_delta += player.speed * TILE_SIZE * time.delta_seconds() * speed_modif;
+        x_velocity += player.speed * speed_modif;
     }
-    let target = transform.translation + Vec3::new(x_delta, 0.0, 0.0);
 
-    if x_delta != 0.0 {
+    if x_velocity != 0.0 {
         *player_state = PlayerState::Moving;
-        if x_delta > 0.0 {
+        if x_velocity > 0.0 {
             *facing = Facing::Right;
-        } else if x_delta < 0.0 {
+        } else if x_velocity < 0.0 {
             *facing = Facing::Left;
         }
-        transform.translation = target;
     }
+    **velocity = Vec2::new(x_velocity, y_velocity);
 }
 /// Spawns the player sprite
 fn spawn_player(
@@ -394,6 +646,18 @@ fn spawn_player(
         damage: Damage::new(10.0),
         level: Level::new(1),
         xp: Experience::new(0.0),
+        lifesteal: Lifesteal::new(PLAYER_LIFESTEAL),
+        potions: Potions::new(STARTING_POTIONS),
+        mend_cooldown: MendCooldown::default(),
+        i_frames: InvulnerabilityFrames::new(PLAYER_I_FRAMES),
+        velocity: Velocity::default(),
+        faction: Faction::Player,
+        collider: Collider::capsule(TILE_SIZE * 0.2, TILE_SIZE * 0.25)
+            // Only detected by projectiles, melee reach is checked by the attacker
+            .with_layers(PLAYER_LAYER, 0),
     };
-    commands.spawn(player).insert(Name::new("player"));
+    commands
+        .spawn(player)
+        .insert(Invulnerable::new(SPAWN_PROTECTION))
+        .insert(Name::new("player"));
 }
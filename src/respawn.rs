@@ -0,0 +1,141 @@
+use crate::elite::{Elite, ELITE_CHANCE};
+use crate::enemy::{spawn_enemy, EnemyDefeatedEvent, EnemySheets, EnemyType};
+use crate::enemy_catalog::{catalog_loaded, EnemyCatalog, EnemyCatalogHandle};
+use crate::spawner::random_facing;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+
+/// Extra distance outside the screen a slot must be at to respawn
+const VIEW_MARGIN: f32 = TILE_SIZE;
+
+pub struct RespawnPlugin;
+
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnManager>().add_systems(
+            Update,
+            (
+                start_respawn_timers,
+                respawn_enemies.run_if(catalog_loaded.and_then(resource_exists::<EnemySheets>)),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Debug)]
+/// Position an enemy was spawned at, repopulated some time after its death
+pub struct SpawnSlot {
+    pub enemy_type: EnemyType,
+    pub level: u32,
+    pub coordinates: Vec3,
+    /// Time before repopulating the slot once empty, in seconds
+    pub delay: f32,
+    /// Running while the slot is empty
+    timer: Option<Timer>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RespawnManager {
+    slots: Vec<SpawnSlot>,
+}
+
+impl RespawnManager {
+    /// Remembers a spawn slot, returns its id
+    pub fn register(
+        &mut self,
+        enemy_type: EnemyType,
+        level: u32,
+        coordinates: Vec3,
+        delay: f32,
+    ) -> SpawnSlotId {
+        self.slots.push(SpawnSlot {
+            enemy_type,
+            level,
+            coordinates,
+            delay,
+            timer: None,
+        });
+        SpawnSlotId(self.slots.len() - 1)
+    }
+    /// Forgets every slot, when leaving the map they are on
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+/// Slot an enemy was spawned from
+pub struct SpawnSlotId(usize);
+
+fn start_respawn_timers(
+    mut events: EventReader<EnemyDefeatedEvent>,
+    mut manager: ResMut<RespawnManager>,
+    q_slots: Query<&SpawnSlotId>,
+) {
+    for event in events.read() {
+        let Ok(id) = q_slots.get(event.entity) else {
+            continue;
+        };
+        if let Some(slot) = manager.slots.get_mut(id.0) {
+            slot.timer = Some(Timer::from_seconds(slot.delay, TimerMode::Once));
+        }
+    }
+}
+
+/// Repopulates empty slots once their delay is over, out of the player's sight
+#[allow(clippy::too_many_arguments)]
+fn respawn_enemies(
+    mut commands: Commands,
+    mut manager: ResMut<RespawnManager>,
+    q_camera: Query<&Transform, With<Camera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+    time: Res<Time>,
+) {
+    let (Some(catalog), Ok(camera), Ok(window)) = (
+        catalogs.get(&**catalog),
+        q_camera.get_single(),
+        window.get_single(),
+    ) else {
+        return;
+    };
+    let half_view = Vec2::new(window.width(), window.height()) / 2.0 + VIEW_MARGIN;
+    let mut rng = rand::thread_rng();
+    for (index, slot) in manager.slots.iter_mut().enumerate() {
+        let Some(timer) = slot.timer.as_mut() else {
+            continue;
+        };
+        // The timer keeps running while in view, the slot fills as soon as the player leaves
+        if !timer.tick(time.delta()).finished() {
+            continue;
+        }
+        let offset = (slot.coordinates - camera.translation).truncate().abs();
+        if offset.x < half_view.x && offset.y < half_view.y {
+            continue;
+        }
+        let (Some(definition), Some(sheet)) =
+            (catalog.get(slot.enemy_type), sheets.get(&slot.enemy_type))
+        else {
+            continue;
+        };
+        slot.timer = None;
+        let entity = spawn_enemy(
+            &mut commands,
+            slot.enemy_type,
+            definition,
+            sheet,
+            slot.coordinates,
+            random_facing(&mut rng),
+            slot.level,
+        );
+        commands.entity(entity).insert(SpawnSlotId(index));
+        if definition.boss.is_none() && rng.gen_bool(ELITE_CHANCE) {
+            commands.entity(entity).insert(Elite::random(&mut rng));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Buckets positions in a uniform grid, so that neighbours are found
+/// without testing every pair of entities
+#[derive(Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+    pub fn clear(&mut self) {
+        // Cells used since the last clear keep their allocation for the next frame
+        self.cells.retain(|_, entities| !entities.is_empty());
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+    fn cells_between(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &(Entity, Vec2)> + '_ {
+        let min = self.cell(min);
+        let max = self.cell(max);
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+    /// Entities within `radius` of `position`
+    pub fn query_radius(
+        &self,
+        position: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.cells_between(position - radius, position + radius)
+            .copied()
+            .filter(move |(_, other)| other.distance_squared(position) <= radius * radius)
+    }
+    /// Entities inside the axis aligned box going from `min` to `max`
+    pub fn query_aabb(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.cells_between(min, max)
+            .copied()
+            .filter(move |(_, position)| position.cmpge(min).all() && position.cmple(max).all())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> (SpatialHash, Vec<(Entity, Vec2)>) {
+        let mut hash = SpatialHash::new(64.0);
+        let points = (0..400)
+            .map(|i| {
+                let position = Vec2::new((i % 20) as f32 * 23.0 - 200.0, (i / 20) as f32 * 17.0);
+                (Entity::from_raw(i), position)
+            })
+            .collect::<Vec<_>>();
+        for (entity, position) in points.iter() {
+            hash.insert(*entity, *position);
+        }
+        (hash, points)
+    }
+
+    fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn radius_query_matches_brute_force() {
+        let (hash, points) = grid();
+        let (center, radius) = (Vec2::new(-30.0, 100.0), 90.0);
+        let expected = points
+            .iter()
+            .filter(|(_, p)| p.distance(center) <= radius)
+            .map(|(e, _)| *e)
+            .collect();
+        let found = hash.query_radius(center, radius).map(|(e, _)| e).collect();
+        assert_eq!(sorted(found), sorted(expected));
+    }
+
+    #[test]
+    fn aabb_query_matches_brute_force() {
+        let (hash, points) = grid();
+        let (min, max) = (Vec2::new(-150.0, 20.0), Vec2::new(10.0, 130.0));
+        let expected = points
+            .iter()
+            .filter(|(_, p)| p.x >= min.x && p.y >= min.y && p.x <= max.x && p.y <= max.y)
+            .map(|(e, _)| *e)
+            .collect();
+        let found = hash.query_aabb(min, max).map(|(e, _)| e).collect();
+        assert_eq!(sorted(found), sorted(expected));
+    }
+}
@@ -0,0 +1,39 @@
+use crate::collision::Collider;
+use crate::entities::{Dead, Health};
+use crate::spatial::SpatialHash;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+
+/// Size of a grid cell, in pixels
+const CELL_SIZE: f32 = TILE_SIZE * 2.0;
+
+pub struct SpatialGridPlugin;
+
+impl Plugin for SpatialGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>()
+            .add_systems(PreUpdate, update_spatial_grid);
+    }
+}
+
+#[derive(Resource, Deref)]
+/// Positions of every living entity and collider, rebuilt at the start of each frame.
+/// Used for collision, aggro and steering instead of testing every pair of entities
+pub struct SpatialGrid(SpatialHash);
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self(SpatialHash::new(CELL_SIZE))
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    q_bodies: Query<(Entity, &Transform), (Or<(With<Health>, With<Collider>)>, Without<Dead>)>,
+) {
+    grid.0.clear();
+    for (entity, transform) in q_bodies.iter() {
+        grid.0.insert(entity, transform.translation.truncate());
+    }
+}
@@ -0,0 +1,528 @@
+use crate::elite::{Elite, ELITE_CHANCE};
+use crate::enemy::{spawn_enemy, EnemySheets, EnemyType};
+use crate::enemy_catalog::{catalog_loaded, EnemyCatalog, EnemyCatalogHandle};
+use crate::entities::{Dead, Facing};
+use crate::respawn::RespawnManager;
+use crate::ron_asset::RonAssetLoader;
+use crate::tiled::{apply_map, MapChangedEvent};
+use crate::tilemap::TileMap;
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+const SPAWN_CONFIG_PATH: &str = "spawns.ron";
+/// Toggles the arena mode
+const ARENA_KEY: KeyCode = KeyCode::F2;
+/// Time between a spawn telegraph appearing and the enemy spawning, in seconds
+const TELEGRAPH_DURATION: f32 = 0.8;
+const TELEGRAPH_RADIUS: f32 = TILE_SIZE * 0.6;
+const TELEGRAPH_COLOR: Color = Color::rgb(0.7, 0.3, 1.0);
+/// Positions drawn before giving up on finding one out of the walls
+const SAMPLE_ATTEMPTS: usize = 10;
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpawnConfig>()
+            .register_asset_loader(RonAssetLoader::<SpawnConfig>::new(&["spawns.ron"]))
+            .init_resource::<WaveSpawner>()
+            .add_systems(Startup, (load_spawn_config, setup_wave_ui))
+            .add_systems(
+                Update,
+                // Spawn points are checked against the terrain inserted by `apply_map`
+                populate_regions.after(apply_map).run_if(
+                    catalog_loaded
+                        .and_then(config_loaded)
+                        .and_then(resource_exists::<EnemySheets>)
+                        .and_then(resource_exists::<TileMap>),
+                ),
+            )
+            .add_systems(
+                Update,
+                (toggle_arena, run_waves).chain().run_if(config_loaded),
+            )
+            .add_systems(
+                Update,
+                resolve_telegraphs.run_if(catalog_loaded.and_then(resource_exists::<EnemySheets>)),
+            )
+            .add_systems(Update, (draw_telegraphs, update_wave_ui));
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug)]
+/// Where and what enemies spawn, loaded from `assets/spawns.ron`
+pub struct SpawnConfig {
+    /// Zones populated once when the game starts
+    pub regions: Vec<SpawnRegion>,
+    #[serde(default)]
+    pub arena: Option<ArenaConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum SpawnArea {
+    /// Random positions inside a rectangle, in pixels
+    Rect { min: (f32, f32), max: (f32, f32) },
+    /// One of the given positions, in pixels
+    Points(Vec<(f32, f32)>),
+}
+
+impl SpawnArea {
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<Vec3> {
+        match self {
+            SpawnArea::Rect { min, max } => {
+                // The corners can be given in any order
+                let (min, max) = (Vec2::from(*min), Vec2::from(*max));
+                let (min, max) = (min.min(max), min.max(max));
+                Some(Vec3::new(
+                    rng.gen_range(min.x..=max.x),
+                    rng.gen_range(min.y..=max.y),
+                    0.0,
+                ))
+            }
+            SpawnArea::Points(points) => points.choose(rng).map(|(x, y)| Vec3::new(*x, *y, 0.0)),
+        }
+    }
+    /// Like `sample`, avoiding the tiles blocking movement
+    pub fn sample_open(&self, rng: &mut impl Rng, tilemap: &TileMap) -> Option<Vec3> {
+        (0..SAMPLE_ATTEMPTS)
+            .filter_map(|_| self.sample(rng))
+            .find(|position| !tilemap.blocks_movement_at(position.truncate()))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpawnRegion {
+    pub name: String,
+    /// Asset path of the map the region is on, every map when unset
+    #[serde(default)]
+    pub map: Option<String>,
+    pub area: SpawnArea,
+    pub count: u32,
+    pub table: SpawnTable,
+    /// Time before a killed enemy respawns at its position, in seconds, never when unset
+    #[serde(default)]
+    pub respawn: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(transparent)]
+/// Enemies that can spawn, picked according to their weight
+pub struct SpawnTable(Vec<SpawnEntry>);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpawnEntry {
+    pub enemy: EnemyType,
+    pub weight: f32,
+    /// Level range of the spawned enemies, the catalog level when unset
+    #[serde(default)]
+    pub levels: Option<(u32, u32)>,
+}
+
+impl SpawnTable {
+    /// Picks an enemy type and its level, if set by the table
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<(EnemyType, Option<u32>)> {
+        let entry = self.0.choose_weighted(rng, |entry| entry.weight).ok()?;
+        let level = entry
+            .levels
+            .map(|(min, max)| rng.gen_range(min..=max.max(min)));
+        Some((entry.enemy, level))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+/// Survival mode, waves of enemies spawn until the player stops it
+pub struct ArenaConfig {
+    pub spawn_points: SpawnArea,
+    /// Maximum number of arena enemies alive at once
+    pub max_alive: u32,
+    /// Time between two spawns of a wave, in seconds
+    pub interval: f32,
+    pub waves: Vec<WaveDefinition>,
+    /// Applied once per wave after the last defined one, which then repeats
+    pub escalation: WaveEscalation,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveDefinition {
+    /// Time before the wave starts, in seconds
+    pub delay: f32,
+    pub count: u32,
+    pub table: SpawnTable,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WaveEscalation {
+    /// Extra enemies per wave
+    pub count: u32,
+    /// Extra enemy levels per wave
+    pub levels: u32,
+}
+
+impl ArenaConfig {
+    /// Definition of the given wave and the number of escalations applied to it
+    fn wave(&self, wave: usize) -> Option<(&WaveDefinition, u32)> {
+        let last = self.waves.len().checked_sub(1)?;
+        Some((
+            &self.waves[wave.min(last)],
+            wave.saturating_sub(last) as u32,
+        ))
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct SpawnConfigHandle(Handle<SpawnConfig>);
+
+#[derive(Debug, Default)]
+enum WavePhase {
+    #[default]
+    Inactive,
+    /// Waiting for the wave delay
+    Waiting(Timer),
+    Spawning {
+        spawned: u32,
+        timer: Timer,
+    },
+    /// Every enemy of the wave is out, waiting for them to die
+    Clearing,
+}
+
+#[derive(Resource, Debug, Default)]
+/// State of the arena mode
+pub struct WaveSpawner {
+    /// Index of the current wave, starting at 0
+    pub wave: usize,
+    phase: WavePhase,
+}
+
+impl WaveSpawner {
+    pub fn active(&self) -> bool {
+        !matches!(self.phase, WavePhase::Inactive)
+    }
+}
+
+#[derive(Component)]
+/// Enemy spawned by the arena mode
+pub struct ArenaEnemy;
+
+#[derive(Component)]
+/// Marks where an enemy is about to spawn
+pub struct SpawnTelegraph {
+    timer: Timer,
+    enemy_type: EnemyType,
+    level: Option<u32>,
+    /// Levels added to the spawned enemy
+    level_bonus: u32,
+    arena: bool,
+}
+
+#[derive(Component)]
+struct WaveText;
+
+fn load_spawn_config(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SpawnConfigHandle(asset_server.load(SPAWN_CONFIG_PATH)));
+}
+
+pub fn config_loaded(
+    handle: Option<Res<SpawnConfigHandle>>,
+    configs: Res<Assets<SpawnConfig>>,
+) -> bool {
+    handle.is_some_and(|handle| configs.contains(&**handle))
+}
+
+pub fn random_facing(rng: &mut impl Rng) -> Facing {
+    match rng.gen_range(0..4) {
+        0 => Facing::Up,
+        1 => Facing::Down,
+        2 => Facing::Left,
+        _ => Facing::Right,
+    }
+}
+
+/// Spawns the population of the regions of every map entered
+#[allow(clippy::too_many_arguments)]
+fn populate_regions(
+    mut commands: Commands,
+    mut respawns: ResMut<RespawnManager>,
+    configs: Res<Assets<SpawnConfig>>,
+    config: Res<SpawnConfigHandle>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+    tilemap: Res<TileMap>,
+    mut events: EventReader<MapChangedEvent>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let (Some(config), Some(catalog)) = (configs.get(&**config), catalogs.get(&**catalog)) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    let regions = config
+        .regions
+        .iter()
+        .filter(|region| region.map.as_ref().is_none_or(|map| *map == event.path));
+    for region in regions {
+        for _ in 0..region.count {
+            let (Some((enemy_type, level)), Some(coordinates)) = (
+                region.table.roll(&mut rng),
+                region.area.sample_open(&mut rng, &tilemap),
+            ) else {
+                warn!("Nothing to spawn in region {}", region.name);
+                break;
+            };
+            let (Some(definition), Some(sheet)) =
+                (catalog.get(enemy_type), sheets.get(&enemy_type))
+            else {
+                continue;
+            };
+            let level = level.unwrap_or(definition.stats.level);
+            let entity = spawn_enemy(
+                &mut commands,
+                enemy_type,
+                definition,
+                sheet,
+                coordinates,
+                random_facing(&mut rng),
+                level,
+            );
+            if let Some(delay) = region.respawn {
+                let slot = respawns.register(enemy_type, level, coordinates, delay);
+                commands.entity(entity).insert(slot);
+            }
+            if definition.boss.is_none() && rng.gen_bool(ELITE_CHANCE) {
+                commands.entity(entity).insert(Elite::random(&mut rng));
+            }
+        }
+    }
+}
+
+fn toggle_arena(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut spawner: ResMut<WaveSpawner>,
+    configs: Res<Assets<SpawnConfig>>,
+    config: Res<SpawnConfigHandle>,
+) {
+    if !keyboard.just_pressed(ARENA_KEY) {
+        return;
+    }
+    if spawner.active() {
+        *spawner = WaveSpawner::default();
+        return;
+    }
+    let Some((wave, _)) = configs
+        .get(&**config)
+        .and_then(|config| config.arena.as_ref())
+        .and_then(|arena| arena.wave(0))
+    else {
+        return;
+    };
+    spawner.wave = 0;
+    spawner.phase = WavePhase::Waiting(Timer::from_seconds(wave.delay, TimerMode::Once));
+}
+
+/// Spawns the arena waves, a wave starts once the previous one is cleared
+fn run_waves(
+    mut commands: Commands,
+    spawner: ResMut<WaveSpawner>,
+    configs: Res<Assets<SpawnConfig>>,
+    config: Res<SpawnConfigHandle>,
+    q_alive: Query<(), (With<ArenaEnemy>, Without<Dead>)>,
+    q_telegraphs: Query<&SpawnTelegraph>,
+    time: Res<Time>,
+) {
+    let spawner = spawner.into_inner();
+    let Some(arena) = configs
+        .get(&**config)
+        .and_then(|config| config.arena.as_ref())
+    else {
+        return;
+    };
+    let Some((wave, escalations)) = arena.wave(spawner.wave) else {
+        return;
+    };
+    let pending = q_telegraphs
+        .iter()
+        .filter(|telegraph| telegraph.arena)
+        .count();
+    let alive = q_alive.iter().count() + pending;
+    let mut rng = rand::thread_rng();
+
+    let next_phase = match &mut spawner.phase {
+        WavePhase::Inactive => None,
+        WavePhase::Waiting(timer) => {
+            timer
+                .tick(time.delta())
+                .finished()
+                .then(|| WavePhase::Spawning {
+                    spawned: 0,
+                    timer: Timer::from_seconds(arena.interval, TimerMode::Repeating),
+                })
+        }
+        WavePhase::Spawning { spawned, timer } => {
+            let count = wave.count + arena.escalation.count * escalations;
+            if timer.tick(time.delta()).just_finished() && alive < arena.max_alive as usize {
+                if let (Some((enemy_type, level)), Some(coordinates)) = (
+                    wave.table.roll(&mut rng),
+                    arena.spawn_points.sample(&mut rng),
+                ) {
+                    commands.spawn((
+                        TransformBundle::from_transform(Transform::from_translation(coordinates)),
+                        SpawnTelegraph {
+                            timer: Timer::from_seconds(TELEGRAPH_DURATION, TimerMode::Once),
+                            enemy_type,
+                            level,
+                            level_bonus: arena.escalation.levels * escalations,
+                            arena: true,
+                        },
+                        Name::new("spawn telegraph"),
+                    ));
+                }
+                *spawned += 1;
+            }
+            (*spawned >= count).then_some(WavePhase::Clearing)
+        }
+        // Wave cleared, on to the next one
+        WavePhase::Clearing if alive == 0 => {
+            spawner.wave += 1;
+            arena.wave(spawner.wave).map(|(next, _)| {
+                WavePhase::Waiting(Timer::from_seconds(next.delay, TimerMode::Once))
+            })
+        }
+        WavePhase::Clearing => None,
+    };
+    if let Some(phase) = next_phase {
+        spawner.phase = phase;
+    }
+}
+
+/// Spawns the enemies announced by finished telegraphs
+fn resolve_telegraphs(
+    mut commands: Commands,
+    mut q_telegraphs: Query<(Entity, &Transform, &mut SpawnTelegraph)>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+    time: Res<Time>,
+) {
+    let Some(catalog) = catalogs.get(&**catalog) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    for (entity, transform, mut telegraph) in q_telegraphs.iter_mut() {
+        if !telegraph.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        let (Some(definition), Some(sheet)) = (
+            catalog.get(telegraph.enemy_type),
+            sheets.get(&telegraph.enemy_type),
+        ) else {
+            continue;
+        };
+        let enemy = spawn_enemy(
+            &mut commands,
+            telegraph.enemy_type,
+            definition,
+            sheet,
+            transform.translation,
+            random_facing(&mut rng),
+            telegraph.level.unwrap_or(definition.stats.level) + telegraph.level_bonus,
+        );
+        if telegraph.arena {
+            commands.entity(enemy).insert(ArenaEnemy);
+        }
+        if definition.boss.is_none() && rng.gen_bool(ELITE_CHANCE) {
+            commands.entity(enemy).insert(Elite::random(&mut rng));
+        }
+    }
+}
+
+/// Telegraphs are drawn as a ring closing in on the spawn position
+fn draw_telegraphs(mut gizmos: Gizmos, q_telegraphs: Query<(&Transform, &SpawnTelegraph)>) {
+    for (transform, telegraph) in q_telegraphs.iter() {
+        let position = transform.translation.truncate();
+        let progress = telegraph.timer.fraction();
+        gizmos.circle_2d(position, TELEGRAPH_RADIUS, TELEGRAPH_COLOR.with_a(0.4));
+        gizmos.circle_2d(
+            position,
+            TELEGRAPH_RADIUS * (2.0 - progress).max(0.1),
+            TELEGRAPH_COLOR,
+        );
+    }
+}
+
+fn setup_wave_ui(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                color: Color::WHITE,
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(100.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        WaveText,
+    ));
+}
+
+fn update_wave_ui(
+    spawner: Res<WaveSpawner>,
+    q_alive: Query<(), (With<ArenaEnemy>, Without<Dead>)>,
+    mut q_text: Query<&mut Text, With<WaveText>>,
+) {
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    text.sections[0].value = match spawner.phase {
+        WavePhase::Inactive => String::new(),
+        WavePhase::Waiting(ref timer) => format!(
+            "Wave {} in {:.0}s",
+            spawner.wave + 1,
+            timer.remaining_secs().ceil()
+        ),
+        _ => format!(
+            "Wave {} - {} left",
+            spawner.wave + 1,
+            q_alive.iter().count()
+        ),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_config_parses() {
+        let config: SpawnConfig = ron::de::from_str(include_str!("../assets/spawns.ron")).unwrap();
+        assert!(!config.regions.is_empty());
+        let arena = config.arena.unwrap();
+        // Waves past the last definition repeat it with escalation
+        let (_, escalations) = arena.wave(arena.waves.len() + 1).unwrap();
+        assert_eq!(escalations, 2);
+    }
+
+    #[test]
+    fn rect_corners_can_be_swapped() {
+        let area = SpawnArea::Rect {
+            min: (10.0, -5.0),
+            max: (-10.0, 5.0),
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..16 {
+            let position = area.sample(&mut rng).unwrap();
+            assert!((-10.0..=10.0).contains(&position.x));
+            assert!((-5.0..=5.0).contains(&position.y));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::ai::run_ai;
+use crate::chunks::Dormant;
+use crate::damage::HitStun;
+use crate::enemy::Enemy;
+use crate::entities::Dead;
+use crate::kinematics::{integrate_velocity, Velocity};
+use crate::spatial_grid::SpatialGrid;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SteeringSettings>()
+            .init_resource::<SteeringSettings>()
+            .add_systems(
+                Update,
+                steer_enemies.after(run_ai).before(integrate_velocity),
+            );
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+/// Crowd steering applied on top of the AI movement, editable from the inspector
+pub struct SteeringSettings {
+    /// Enemies closer than this distance, in pixels, push each other away
+    pub separation_radius: f32,
+    /// Maximum separation speed, in tiles per second
+    pub separation_strength: f32,
+    /// Moving enemies also match the heading of their neighbours and stay grouped
+    pub flocking: bool,
+    /// Distance within which enemies flock together, in pixels
+    pub flocking_radius: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+}
+
+impl Default for SteeringSettings {
+    fn default() -> Self {
+        Self {
+            separation_radius: 40.0,
+            separation_strength: 1.5,
+            flocking: false,
+            flocking_radius: 120.0,
+            alignment_weight: 0.3,
+            cohesion_weight: 0.2,
+        }
+    }
+}
+
+/// Direction to push away from a neighbour standing on the exact same spot, a different
+/// direction per entity (golden angle steps) so that stacked neighbours spread out
+fn tie_breaker(entity: Entity) -> Vec2 {
+    Vec2::from_angle(entity.index() as f32 * 2.399_963)
+}
+
+#[allow(clippy::type_complexity)]
+fn steer_enemies(
+    mut q_enemies: Query<
+        (Entity, &Transform, &mut Velocity),
+        (With<Enemy>, Without<Dead>, Without<Dormant>),
+    >,
+    q_stunned: Query<(), With<HitStun>>,
+    settings: Res<SteeringSettings>,
+    grid: Res<SpatialGrid>,
+) {
+    let radius = settings
+        .separation_radius
+        .max(if settings.flocking {
+            settings.flocking_radius
+        } else {
+            0.0
+        })
+        .max(1.0);
+    let velocities = q_enemies
+        .iter()
+        .map(|(entity, _, velocity)| (entity, **velocity))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, transform, mut velocity) in q_enemies.iter_mut() {
+        // Stunned enemies are only moved by their knockback
+        if q_stunned.contains(entity) {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let mut separation = Vec2::ZERO;
+        let mut heading = Vec2::ZERO;
+        let mut center = Vec2::ZERO;
+        let mut flockmates = 0;
+        for (other, other_position) in grid.query_radius(position, radius) {
+            // The grid also holds the player, only enemies steer around each other
+            if other == entity || !velocities.contains_key(&other) {
+                continue;
+            }
+            let offset = position - other_position;
+            let distance = offset.length();
+            if distance < settings.separation_radius {
+                let away = if distance > f32::EPSILON {
+                    offset / distance
+                } else {
+                    tie_breaker(entity)
+                };
+                separation += away * (1.0 - distance / settings.separation_radius);
+            }
+            if settings.flocking && distance < settings.flocking_radius {
+                heading += velocities[&other];
+                center += other_position;
+                flockmates += 1;
+            }
+        }
+        let mut steering = separation.clamp_length_max(1.0) * settings.separation_strength;
+        if flockmates > 0 && **velocity != Vec2::ZERO {
+            let speed = velocity.length();
+            let heading = heading / flockmates as f32;
+            let to_center = (center / flockmates as f32 - position).normalize_or_zero();
+            steering += (heading - **velocity) * settings.alignment_weight
+                + to_center * speed * settings.cohesion_weight;
+        }
+        **velocity += steering;
+    }
+}
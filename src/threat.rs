@@ -0,0 +1,188 @@
+use crate::chunks::Dormant;
+use crate::damage::DamageDealtEvent;
+use crate::enemy::{Enemy, SPAWN_REACHED_DISTANCE};
+use crate::entities::{Dead, Health};
+use crate::faction::{Faction, FactionRelations};
+use crate::spatial_grid::SpatialGrid;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Threat generated per point of damage dealt
+const DAMAGE_THREAT: f32 = 1.0;
+/// Threat generated per second by a target standing within aggro range
+const PROXIMITY_THREAT: f32 = 10.0;
+/// Allies within this distance of an enemy entering combat join the fight
+const SOCIAL_AGGRO_RANGE: f32 = 120.0;
+/// Threat given to allies pulled into combat through social aggro
+const SOCIAL_THREAT: f32 = 1.0;
+
+pub struct ThreatPlugin;
+
+impl Plugin for ThreatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnterCombatEvent>().add_systems(
+            Update,
+            (
+                damage_threat,
+                proximity_threat,
+                social_aggro,
+                leash,
+                purge_threat,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component, Default, Debug)]
+/// Threat generated by each target on an enemy, the enemy attacks the highest one
+pub struct ThreatTable {
+    threat: HashMap<Entity, f32>,
+}
+
+impl ThreatTable {
+    /// Adds threat for the given target, returns true if the enemy just entered combat
+    pub fn add(&mut self, target: Entity, amount: f32) -> bool {
+        let entered_combat = self.threat.is_empty();
+        *self.threat.entry(target).or_default() += amount;
+        entered_combat
+    }
+    pub fn get(&self, target: Entity) -> f32 {
+        self.threat.get(&target).copied().unwrap_or_default()
+    }
+    /// Target with the most threat
+    pub fn top(&self) -> Option<Entity> {
+        self.threat
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(target, _)| *target)
+    }
+    pub fn in_combat(&self) -> bool {
+        !self.threat.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.threat.clear();
+    }
+}
+
+#[derive(Event)]
+/// Sent when an enemy goes from an empty threat table to a non empty one
+pub struct EnterCombatEvent {
+    pub enemy: Entity,
+    pub target: Entity,
+}
+
+/// Damage pulls enemies into combat, even from outside their aggro range.
+/// Neutral attackers become targets too, only friendly fire is ignored
+fn damage_threat(
+    mut events: EventReader<DamageDealtEvent>,
+    mut combat_events: EventWriter<EnterCombatEvent>,
+    mut q_enemies: Query<(&Enemy, &Faction, &mut ThreatTable), Without<Dead>>,
+    q_factions: Query<&Faction>,
+    relations: Res<FactionRelations>,
+) {
+    for event in events.read() {
+        let Some(source) = event.source else {
+            continue;
+        };
+        if let Ok((enemy, faction, mut threat)) = q_enemies.get_mut(event.target) {
+            let friendly_fire = q_factions
+                .get(source)
+                .is_ok_and(|source_faction| relations.is_friendly(*faction, *source_faction));
+            if enemy.evading || friendly_fire {
+                continue;
+            }
+            if threat.add(source, event.damage * DAMAGE_THREAT) {
+                combat_events.send(EnterCombatEvent {
+                    enemy: event.target,
+                    target: source,
+                });
+            }
+        }
+    }
+}
+
+/// Hostile entities within aggro range slowly build up threat
+#[allow(clippy::type_complexity)]
+fn proximity_threat(
+    mut combat_events: EventWriter<EnterCombatEvent>,
+    mut q_enemies: Query<
+        (Entity, &Transform, &Enemy, &Faction, &mut ThreatTable),
+        (Without<Dead>, Without<Dormant>),
+    >,
+    q_targets: Query<&Faction, (With<Health>, Without<Dead>)>,
+    relations: Res<FactionRelations>,
+    grid: Res<SpatialGrid>,
+    time: Res<Time>,
+) {
+    for (entity, transform, enemy, faction, mut threat) in q_enemies.iter_mut() {
+        if enemy.evading {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        for (target, _) in grid.query_radius(position, enemy.aggro_range) {
+            let Ok(target_faction) = q_targets.get(target) else {
+                continue;
+            };
+            if relations.is_hostile(*faction, *target_faction)
+                && threat.add(target, PROXIMITY_THREAT * time.delta_seconds())
+            {
+                combat_events.send(EnterCombatEvent {
+                    enemy: entity,
+                    target,
+                });
+            }
+        }
+    }
+}
+
+/// Enemies entering combat pull their idle friendly neighbours in with them
+fn social_aggro(
+    mut events: EventReader<EnterCombatEvent>,
+    mut q_enemies: Query<(&Transform, &Enemy, &Faction, &mut ThreatTable), Without<Dead>>,
+    relations: Res<FactionRelations>,
+    grid: Res<SpatialGrid>,
+) {
+    for event in events.read() {
+        let Ok((transform, _, faction, _)) = q_enemies.get(event.enemy) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let faction = *faction;
+        for (ally_entity, _) in grid.query_radius(position, SOCIAL_AGGRO_RANGE) {
+            let Ok((_, ally, ally_faction, mut threat)) = q_enemies.get_mut(ally_entity) else {
+                continue;
+            };
+            if ally.evading || threat.in_combat() || !relations.is_friendly(faction, *ally_faction)
+            {
+                continue;
+            }
+            threat.add(event.target, SOCIAL_THREAT);
+        }
+    }
+}
+
+/// Enemies pulled too far from their spawn drop combat and evade back home
+fn leash(mut q_enemies: Query<(&Transform, &mut Enemy, &mut ThreatTable), Without<Dead>>) {
+    for (transform, mut enemy, mut threat) in q_enemies.iter_mut() {
+        let distance_to_spawn = transform.translation.distance(enemy.spawn_coords);
+        if threat.in_combat() && distance_to_spawn > enemy.leash_range {
+            threat.clear();
+            enemy.evading = true;
+        } else if enemy.evading && distance_to_spawn <= SPAWN_REACHED_DISTANCE {
+            enemy.evading = false;
+        }
+    }
+}
+
+/// Removes dead or despawned targets from the threat tables
+fn purge_threat(
+    mut q_tables: Query<&mut ThreatTable>,
+    q_alive: Query<(), (With<Health>, Without<Dead>)>,
+) {
+    for mut table in q_tables.iter_mut() {
+        if table.threat.keys().any(|target| !q_alive.contains(*target)) {
+            table.threat.retain(|target, _| q_alive.contains(*target));
+        }
+    }
+}
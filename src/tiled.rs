@@ -0,0 +1,725 @@
+use crate::abilities::Projectile;
+use crate::dungeon::{generate_floor, DungeonSeed};
+use crate::enemy::{spawn_enemy, Enemy, EnemySheets, EnemyType};
+use crate::enemy_catalog::{catalog_loaded, EnemyCatalog, EnemyCatalogHandle};
+use crate::loot::LootDrop;
+use crate::player::Player;
+use crate::respawn::RespawnManager;
+use crate::spawner::{config_loaded, random_facing};
+use crate::tilemap::{TileKind, TileMap};
+use crate::TILE_SIZE;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadState};
+use bevy::prelude::*;
+use bevy::utils::{BoxedFuture, HashMap};
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+use serde_json::Value;
+use std::str::FromStr;
+use thiserror::Error;
+
+const START_MAP_PATH: &str = "maps/world.tmj";
+/// Bits of a tile id used by Tiled to flip or rotate the tile
+const GID_FLAGS: u32 = 0xF000_0000;
+
+pub struct TiledPlugin;
+
+impl Plugin for TiledPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TiledMap>()
+            .register_asset_loader(TiledMapLoader)
+            .add_event::<MapChangedEvent>()
+            .add_systems(Startup, load_start_map)
+            .add_systems(
+                Update,
+                (apply_map, spawn_map_enemies).chain().run_if(
+                    map_pending
+                        .and_then(catalog_loaded)
+                        .and_then(config_loaded)
+                        .and_then(resource_exists::<EnemySheets>),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    zone_transitions,
+                    switch_to_pending_map.run_if(resource_exists::<PendingMap>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Enemy placed on the map, spawned every time the map is entered
+pub struct MapEnemySpawn {
+    pub enemy_type: EnemyType,
+    /// The catalog level when unset
+    pub level: Option<u32>,
+    pub position: Vec2,
+    /// Time before the enemy respawns once killed, in seconds, never when unset
+    pub respawn: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Area moving the player to another map
+pub struct ZoneTransition {
+    pub area: Rect,
+    pub target: MapTarget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapTarget {
+    /// Asset path of the map to load
+    File(String),
+    /// Generated dungeon floor, starting at 1
+    DungeonFloor(u32),
+}
+
+#[derive(Asset, TypePath, Debug, PartialEq)]
+/// Level authored in Tiled and saved as JSON (`.tmj`) or XML (`.tmx`), the bottom left corner of the map being at the origin.
+/// Tiles are read from the tileset classes (`wall`, `water`, `tree`, `stairs`, `floor`) of every tile layer,
+/// any tile of a layer with the `collision` property (or named "collision") blocks movement.
+/// Objects are read from their class: `player_spawn`, `enemy_spawn` (`enemy`, `level` and `respawn`
+/// properties) and `zone_transition` (`map` property, or `dungeon` for the depth of a dungeon floor)
+pub struct TiledMap {
+    pub tiles: TileMap,
+    pub player_spawn: Option<Vec2>,
+    pub enemy_spawns: Vec<MapEnemySpawn>,
+    pub transitions: Vec<ZoneTransition>,
+}
+
+#[derive(Debug, Error)]
+pub enum TiledError {
+    #[error("could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not parse the file: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("the file is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("the {0} attribute is missing or invalid")]
+    Attribute(&'static str),
+    #[error("{0} are not supported")]
+    Unsupported(&'static str),
+    #[error("object {object} is missing its {property} property")]
+    MissingProperty {
+        object: String,
+        property: &'static str,
+    },
+}
+
+#[derive(Deserialize)]
+struct RawMap {
+    width: u32,
+    height: u32,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<RawLayer>,
+    #[serde(default)]
+    tilesets: Vec<RawTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RawLayer {
+    Tilelayer {
+        name: String,
+        data: Vec<u32>,
+        #[serde(default)]
+        properties: Vec<RawProperty>,
+    },
+    Objectgroup {
+        objects: Vec<RawObject>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct RawTileset {
+    firstgid: u32,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    tiles: Vec<RawTile>,
+}
+
+#[derive(Deserialize)]
+struct RawTile {
+    id: u32,
+    // Saved as "class" by Tiled 1.9
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    value: Value,
+}
+
+fn property<'a>(properties: &'a [RawProperty], name: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+fn tile_kind(class: &str) -> Option<TileKind> {
+    match class {
+        "floor" => Some(TileKind::Floor),
+        "wall" => Some(TileKind::Wall),
+        "water" => Some(TileKind::Water),
+        "tree" => Some(TileKind::Tree),
+        "stairs" => Some(TileKind::Stairs),
+        _ => None,
+    }
+}
+
+/// Parses an attribute of a TMX element
+fn attribute<T: FromStr>(node: Node, name: &'static str) -> Result<T, TiledError> {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or(TiledError::Attribute(name))
+}
+
+/// Parses an attribute of a TMX element, `None` when missing
+fn optional_attribute<T: FromStr>(node: Node, name: &'static str) -> Result<Option<T>, TiledError> {
+    node.attribute(name)
+        .map(|value| value.parse().map_err(|_| TiledError::Attribute(name)))
+        .transpose()
+}
+
+/// Class of a TMX element, saved as "class" since Tiled 1.9
+fn class(node: Node) -> String {
+    node.attribute("class")
+        .or(node.attribute("type"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+/// Properties of a TMX element, typed like the JSON ones
+fn properties(node: Node) -> Vec<RawProperty> {
+    children(node, "properties")
+        .flat_map(|properties| children(properties, "property"))
+        .map(|property| {
+            let value = property.attribute("value").unwrap_or_default();
+            let value = match property.attribute("type") {
+                Some("bool") => Value::Bool(value == "true"),
+                Some("int") => value.parse::<i64>().map_or(Value::Null, Value::from),
+                Some("float") => value.parse::<f64>().map_or(Value::Null, Value::from),
+                _ => Value::String(value.to_string()),
+            };
+            RawProperty {
+                name: property.attribute("name").unwrap_or_default().to_string(),
+                value,
+            }
+        })
+        .collect()
+}
+
+/// Tile ids of a TMX tile layer, saved as CSV or as one element per tile
+fn tile_data(layer: Node) -> Result<Vec<u32>, TiledError> {
+    let Some(data) = children(layer, "data").next() else {
+        return Ok(Vec::new());
+    };
+    match data.attribute("encoding") {
+        Some("csv") => data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(|gid| {
+                gid.trim()
+                    .parse()
+                    .map_err(|_| TiledError::Attribute("data"))
+            })
+            .collect(),
+        None => children(data, "tile")
+            .map(|tile| Ok(optional_attribute(tile, "gid")?.unwrap_or(0)))
+            .collect(),
+        Some(_) => Err(TiledError::Unsupported("base64 tile layers")),
+    }
+}
+
+impl TiledMap {
+    pub fn from_json(bytes: &[u8]) -> Result<Self, TiledError> {
+        Self::from_raw(serde_json::from_slice(bytes)?)
+    }
+
+    pub fn from_tmx(text: &str) -> Result<Self, TiledError> {
+        let document = Document::parse(text)?;
+        let root = document.root_element();
+        let mut layers = Vec::new();
+        let mut tilesets = Vec::new();
+        for node in root.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "tileset" => tilesets.push(RawTileset {
+                    firstgid: attribute(node, "firstgid")?,
+                    source: node.attribute("source").map(str::to_string),
+                    tiles: children(node, "tile")
+                        .map(|tile| {
+                            Ok(RawTile {
+                                id: attribute(tile, "id")?,
+                                class: class(tile),
+                            })
+                        })
+                        .collect::<Result<_, TiledError>>()?,
+                }),
+                "layer" => layers.push(RawLayer::Tilelayer {
+                    name: node.attribute("name").unwrap_or_default().to_string(),
+                    data: tile_data(node)?,
+                    properties: properties(node),
+                }),
+                "objectgroup" => layers.push(RawLayer::Objectgroup {
+                    objects: children(node, "object")
+                        .map(|object| {
+                            Ok(RawObject {
+                                name: object.attribute("name").unwrap_or_default().to_string(),
+                                class: class(object),
+                                x: attribute(object, "x")?,
+                                y: attribute(object, "y")?,
+                                width: optional_attribute(object, "width")?.unwrap_or(0.0),
+                                height: optional_attribute(object, "height")?.unwrap_or(0.0),
+                                properties: properties(object),
+                            })
+                        })
+                        .collect::<Result<_, TiledError>>()?,
+                }),
+                _ => layers.push(RawLayer::Other),
+            }
+        }
+        Self::from_raw(RawMap {
+            width: attribute(root, "width")?,
+            height: attribute(root, "height")?,
+            tilewidth: attribute(root, "tilewidth")?,
+            tileheight: attribute(root, "tileheight")?,
+            infinite: optional_attribute::<u8>(root, "infinite")?
+                .is_some_and(|infinite| infinite != 0),
+            layers,
+            tilesets,
+        })
+    }
+
+    fn from_raw(raw: RawMap) -> Result<Self, TiledError> {
+        if raw.infinite {
+            return Err(TiledError::Unsupported("infinite maps"));
+        }
+        let mut kinds = HashMap::new();
+        for tileset in raw.tilesets.iter() {
+            if tileset.source.is_some() {
+                return Err(TiledError::Unsupported("external tilesets"));
+            }
+            for tile in tileset.tiles.iter() {
+                if let Some(kind) = tile_kind(&tile.class) {
+                    kinds.insert(tileset.firstgid + tile.id, kind);
+                }
+            }
+        }
+
+        // Tiled counts rows from the top, in pixels of its own tile size
+        let scale = Vec2::new(TILE_SIZE / raw.tilewidth, TILE_SIZE / raw.tileheight);
+        let height = raw.height as f32 * raw.tileheight;
+        let to_world = |x: f32, y: f32| Vec2::new(x, height - y) * scale;
+
+        let mut map = TiledMap {
+            tiles: TileMap::new(Vec2::ZERO, UVec2::new(raw.width, raw.height)),
+            player_spawn: None,
+            enemy_spawns: Vec::new(),
+            transitions: Vec::new(),
+        };
+        for layer in raw.layers {
+            match layer {
+                RawLayer::Tilelayer {
+                    name,
+                    data,
+                    properties,
+                } => {
+                    let collision = name.eq_ignore_ascii_case("collision")
+                        || property(&properties, "collision") == Some(&Value::Bool(true));
+                    for (index, gid) in data.into_iter().enumerate() {
+                        let gid = gid & !GID_FLAGS;
+                        if gid == 0 {
+                            continue;
+                        }
+                        let kind = kinds.get(&gid).copied();
+                        let kind = if collision {
+                            kind.filter(TileKind::blocks_movement)
+                                .unwrap_or(TileKind::Wall)
+                        } else if let Some(kind) = kind {
+                            kind
+                        } else {
+                            continue;
+                        };
+                        let index = index as u32;
+                        let Some(row) = raw.height.checked_sub(index / raw.width + 1) else {
+                            break;
+                        };
+                        map.tiles
+                            .set(UVec2::new(index % raw.width, row).as_ivec2(), kind);
+                    }
+                }
+                RawLayer::Objectgroup { objects } => {
+                    for object in objects {
+                        map.add_object(object, to_world)?;
+                    }
+                }
+                RawLayer::Other => {}
+            }
+        }
+        Ok(map)
+    }
+
+    fn add_object(
+        &mut self,
+        object: RawObject,
+        to_world: impl Fn(f32, f32) -> Vec2,
+    ) -> Result<(), TiledError> {
+        let area = Rect::from_corners(
+            to_world(object.x, object.y),
+            to_world(object.x + object.width, object.y + object.height),
+        );
+        let missing = |property| TiledError::MissingProperty {
+            object: format!("{} ({})", object.name, object.class),
+            property,
+        };
+        match object.class.as_str() {
+            "player_spawn" => self.player_spawn = Some(area.center()),
+            "enemy_spawn" => {
+                let enemy =
+                    property(&object.properties, "enemy").ok_or_else(|| missing("enemy"))?;
+                self.enemy_spawns.push(MapEnemySpawn {
+                    enemy_type: serde_json::from_value(enemy.clone())?,
+                    level: property(&object.properties, "level")
+                        .and_then(Value::as_u64)
+                        .map(|level| level as u32),
+                    position: area.center(),
+                    respawn: property(&object.properties, "respawn")
+                        .and_then(Value::as_f64)
+                        .map(|respawn| respawn as f32),
+                });
+            }
+            "zone_transition" => {
+                let map = property(&object.properties, "map").and_then(Value::as_str);
+                let depth = property(&object.properties, "dungeon").and_then(Value::as_u64);
+                let target = match (map, depth) {
+                    (Some(map), _) => MapTarget::File(map.to_string()),
+                    (None, Some(depth)) => MapTarget::DungeonFloor(depth as u32),
+                    (None, None) => return Err(missing("map")),
+                };
+                self.transitions.push(ZoneTransition { area, target });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = TiledMap;
+    type Settings = ();
+    type Error = TiledError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<TiledMap, TiledError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let xml = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "tmx");
+            if xml {
+                TiledMap::from_tmx(std::str::from_utf8(&bytes)?)
+            } else {
+                TiledMap::from_json(&bytes)
+            }
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj", "tmx"]
+    }
+}
+
+#[derive(Resource, Debug)]
+/// Map the player is on, applied to the world once loaded
+pub struct CurrentMap {
+    pub path: String,
+    handle: Handle<TiledMap>,
+    applied: bool,
+}
+
+impl CurrentMap {
+    fn load(asset_server: &AssetServer, path: String) -> Self {
+        Self::new(path.clone(), asset_server.load(path))
+    }
+    fn new(path: String, handle: Handle<TiledMap>) -> Self {
+        Self {
+            path,
+            handle,
+            applied: false,
+        }
+    }
+    /// Where the player enters the map, once it is loaded
+    pub fn player_spawn(&self, maps: &Assets<TiledMap>) -> Option<Vec2> {
+        maps.get(&self.handle).and_then(|map| map.player_spawn)
+    }
+}
+
+#[derive(Resource, Debug)]
+/// Map a transition leads to, the current map is kept until it is loaded
+struct PendingMap {
+    path: String,
+    handle: Handle<TiledMap>,
+}
+
+#[derive(Event, Debug)]
+/// Sent once a new map replaced the previous one
+pub struct MapChangedEvent {
+    /// Asset path of the new map, or the name of a generated one
+    pub path: String,
+}
+
+fn load_start_map(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CurrentMap::load(&asset_server, START_MAP_PATH.to_string()));
+}
+
+fn map_pending(current: Option<Res<CurrentMap>>, maps: Res<Assets<TiledMap>>) -> bool {
+    current.is_some_and(|current| !current.applied && maps.contains(&current.handle))
+}
+
+/// Replaces the terrain and clears everything left on the previous map
+#[allow(clippy::type_complexity)]
+pub fn apply_map(
+    mut commands: Commands,
+    mut current: ResMut<CurrentMap>,
+    maps: Res<Assets<TiledMap>>,
+    mut q_player: Query<&mut Transform, With<Player>>,
+    q_leftovers: Query<Entity, Or<(With<Enemy>, With<LootDrop>, With<Projectile>)>>,
+    mut respawns: ResMut<RespawnManager>,
+    mut events: EventWriter<MapChangedEvent>,
+) {
+    let Some(map) = maps.get(&current.handle) else {
+        return;
+    };
+    current.applied = true;
+    for entity in q_leftovers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawns.clear();
+    commands.insert_resource(map.tiles.clone());
+    if let (Some(spawn), Ok(mut transform)) = (map.player_spawn, q_player.get_single_mut()) {
+        transform.translation = spawn.extend(transform.translation.z);
+    }
+    events.send(MapChangedEvent {
+        path: current.path.clone(),
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_map_enemies(
+    mut commands: Commands,
+    mut events: EventReader<MapChangedEvent>,
+    mut respawns: ResMut<RespawnManager>,
+    current: Res<CurrentMap>,
+    maps: Res<Assets<TiledMap>>,
+    catalogs: Res<Assets<EnemyCatalog>>,
+    catalog: Res<EnemyCatalogHandle>,
+    sheets: Res<EnemySheets>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let (Some(map), Some(catalog)) = (maps.get(&current.handle), catalogs.get(&**catalog)) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    for spawn in map.enemy_spawns.iter() {
+        let (Some(definition), Some(sheet)) =
+            (catalog.get(spawn.enemy_type), sheets.get(&spawn.enemy_type))
+        else {
+            continue;
+        };
+        let level = spawn.level.unwrap_or(definition.stats.level);
+        let coordinates = spawn.position.extend(0.0);
+        let entity = spawn_enemy(
+            &mut commands,
+            spawn.enemy_type,
+            definition,
+            sheet,
+            coordinates,
+            random_facing(&mut rng),
+            level,
+        );
+        if let Some(delay) = spawn.respawn {
+            let slot = respawns.register(spawn.enemy_type, level, coordinates, delay);
+            commands.entity(entity).insert(slot);
+        }
+    }
+}
+
+/// Loads the target map of the transition the player walks into. Only entering an area counts,
+/// so a player arriving inside one (e.g. on a map without a player spawn) is not sent back
+#[allow(clippy::too_many_arguments)]
+fn zone_transitions(
+    mut commands: Commands,
+    mut current: ResMut<CurrentMap>,
+    mut maps: ResMut<Assets<TiledMap>>,
+    q_player: Query<&Transform, With<Player>>,
+    asset_server: Res<AssetServer>,
+    seed: Res<DungeonSeed>,
+    pending: Option<Res<PendingMap>>,
+    mut in_transition: Local<bool>,
+) {
+    if !current.applied || pending.is_some() {
+        return;
+    }
+    let (Some(map), Ok(player)) = (maps.get(&current.handle), q_player.get_single()) else {
+        return;
+    };
+    let position = player.translation.truncate();
+    let transition = map.transitions.iter().find(|t| t.area.contains(position));
+    let entered = transition.is_some() && !*in_transition;
+    *in_transition = transition.is_some();
+    let Some(transition) = transition.filter(|_| entered) else {
+        return;
+    };
+    match transition.target.clone() {
+        MapTarget::File(path) => commands.insert_resource(PendingMap {
+            handle: asset_server.load(path.clone()),
+            path,
+        }),
+        MapTarget::DungeonFloor(depth) => {
+            let floor = generate_floor(seed.0, depth);
+            *current = CurrentMap::new(format!("dungeon floor {depth}"), maps.add(floor));
+        }
+    }
+}
+
+/// Replaces the current map with the pending one once loaded, or drops it if it failed to load
+fn switch_to_pending_map(
+    mut commands: Commands,
+    pending: Res<PendingMap>,
+    mut current: ResMut<CurrentMap>,
+    maps: Res<Assets<TiledMap>>,
+    asset_server: Res<AssetServer>,
+) {
+    if maps.contains(&pending.handle) {
+        *current = CurrentMap::new(pending.path.clone(), pending.handle.clone());
+    } else if asset_server.load_state(&pending.handle) == LoadState::Failed {
+        error!(
+            "Could not load the map {}, staying on {}",
+            pending.path, current.path
+        );
+    } else {
+        return;
+    }
+    commands.remove_resource::<PendingMap>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The map of `objects_are_flipped_and_scaled`, saved as TMX
+    const FLIPPED_TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <map width="2" height="2" tilewidth="32" tileheight="32" infinite="0">
+            <layer name="collision"><data encoding="csv">1,0,0,0</data></layer>
+            <objectgroup>
+                <object type="player_spawn" x="16" y="48"><point/></object>
+            </objectgroup>
+        </map>"#;
+
+    #[test]
+    fn crypt_map_parses() {
+        let map = TiledMap::from_tmx(include_str!("../assets/maps/crypt.tmx")).unwrap();
+        assert_eq!(map.enemy_spawns.len(), 3);
+        assert_eq!(map.enemy_spawns[2].enemy_type, EnemyType::SkeletonArcher);
+        assert_eq!(map.enemy_spawns[2].level, Some(5));
+        assert_eq!(map.enemy_spawns[2].respawn, Some(60.0));
+        assert_eq!(map.tiles.get(IVec2::new(7, 5)), TileKind::Water);
+        assert_eq!(
+            map.transitions[0].target,
+            MapTarget::File("maps/world.tmj".to_string())
+        );
+    }
+
+    #[test]
+    fn tmx_tiles_can_be_elements() {
+        let tmx = r#"<map width="2" height="1" tilewidth="32" tileheight="32">
+            <layer name="collision"><data><tile/><tile gid="1"/></data></layer>
+        </map>"#;
+        let map = TiledMap::from_tmx(tmx).unwrap();
+        assert_eq!(map.tiles.get(IVec2::new(0, 0)), TileKind::Floor);
+        assert_eq!(map.tiles.get(IVec2::new(1, 0)), TileKind::Wall);
+    }
+
+    #[test]
+    fn world_map_parses() {
+        let map = TiledMap::from_json(include_bytes!("../assets/maps/world.tmj"))
+            .expect("invalid world map");
+        let spawn = map.player_spawn.expect("the world has no player spawn");
+        assert!(
+            !map.tiles.blocks_movement_at(spawn),
+            "the player spawns in a wall"
+        );
+        assert!(!map.enemy_spawns.is_empty());
+        assert!(!map.transitions.is_empty());
+    }
+
+    #[test]
+    fn objects_are_flipped_and_scaled() {
+        let json = r#"{
+            "width": 2, "height": 2, "tilewidth": 32, "tileheight": 32,
+            "layers": [
+                {"type": "tilelayer", "name": "collision", "data": [1, 0, 0, 0]},
+                {"type": "objectgroup", "objects": [
+                    {"type": "player_spawn", "x": 16, "y": 48, "point": true}
+                ]}
+            ]
+        }"#;
+        let map = TiledMap::from_json(json.as_bytes()).unwrap();
+        assert_eq!(map, TiledMap::from_tmx(FLIPPED_TMX).unwrap());
+        // The first tile of the data is the top left one
+        assert_eq!(map.tiles.get(IVec2::new(0, 1)), TileKind::Wall);
+        assert_eq!(map.tiles.get(IVec2::new(0, 0)), TileKind::Floor);
+        assert_eq!(
+            map.player_spawn,
+            Some(Vec2::new(TILE_SIZE * 0.5, TILE_SIZE * 0.5))
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::chunks::{update_chunks, ChunkManager};
+use crate::TILE_SIZE;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+/// Tiles are drawn under every entity
+const TILE_Z: f32 = -10.0;
+/// Keeps boxes touching a tile edge from counting as overlapping it, in pixels
+const EDGE_EPSILON: f32 = 0.01;
+
+pub struct TilemapPlugin;
+
+impl Plugin for TilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            stream_tile_sprites
+                .after(update_chunks)
+                .run_if(resource_exists::<TileMap>),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileKind {
+    #[default]
+    Floor,
+    Wall,
+    Water,
+    Tree,
+    /// Leads to the next dungeon floor
+    Stairs,
+}
+
+impl TileKind {
+    pub fn blocks_movement(&self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::Water | TileKind::Tree)
+    }
+    /// Projectiles fly over water
+    pub fn blocks_projectiles(&self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::Tree)
+    }
+    fn color(&self) -> Option<Color> {
+        match self {
+            TileKind::Floor => None,
+            TileKind::Wall => Some(Color::rgb(0.35, 0.33, 0.32)),
+            TileKind::Water => Some(Color::rgb(0.2, 0.35, 0.7)),
+            TileKind::Tree => Some(Color::rgb(0.1, 0.4, 0.15)),
+            TileKind::Stairs => Some(Color::rgb(0.75, 0.6, 0.2)),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+/// Terrain of the current map, in tiles of `TILE_SIZE` pixels. Everything outside is floor
+pub struct TileMap {
+    /// Bottom left corner of the tile (0, 0), in pixels
+    origin: Vec2,
+    size: UVec2,
+    tiles: Vec<TileKind>,
+}
+
+impl TileMap {
+    pub fn new(origin: Vec2, size: UVec2) -> Self {
+        Self::filled(origin, size, TileKind::Floor)
+    }
+    pub fn filled(origin: Vec2, size: UVec2, kind: TileKind) -> Self {
+        Self {
+            origin,
+            size,
+            tiles: vec![kind; (size.x * size.y) as usize],
+        }
+    }
+    #[cfg(test)]
+    /// Map drawn with one string per row, top row first: `#` for walls, `~` for water and floor
+    /// for anything else
+    pub fn from_ascii(rows: &[&str]) -> Self {
+        let size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+        let mut map = Self::new(Vec2::ZERO, size);
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let kind = match c {
+                    '#' => TileKind::Wall,
+                    '~' => TileKind::Water,
+                    _ => TileKind::Floor,
+                };
+                map.set(IVec2::new(x as i32, y as i32), kind);
+            }
+        }
+        map
+    }
+    fn index(&self, tile: IVec2) -> Option<usize> {
+        let in_bounds = tile.cmpge(IVec2::ZERO).all() && tile.as_uvec2().cmplt(self.size).all();
+        in_bounds.then(|| (tile.y as u32 * self.size.x + tile.x as u32) as usize)
+    }
+    pub fn get(&self, tile: IVec2) -> TileKind {
+        self.index(tile)
+            .map_or(TileKind::Floor, |index| self.tiles[index])
+    }
+    /// Tiles outside of the map are ignored
+    pub fn set(&mut self, tile: IVec2, kind: TileKind) {
+        if let Some(index) = self.index(tile) {
+            self.tiles[index] = kind;
+        }
+    }
+    pub fn tile_at(&self, position: Vec2) -> IVec2 {
+        ((position - self.origin) / TILE_SIZE).floor().as_ivec2()
+    }
+    pub fn tile_center(&self, tile: IVec2) -> Vec2 {
+        self.origin + (tile.as_vec2() + 0.5) * TILE_SIZE
+    }
+    pub fn blocks_movement_at(&self, position: Vec2) -> bool {
+        self.get(self.tile_at(position)).blocks_movement()
+    }
+    pub fn blocks_projectiles_at(&self, position: Vec2) -> bool {
+        self.get(self.tile_at(position)).blocks_projectiles()
+    }
+    /// Part of `delta` a box centered on `center` can move before hitting a blocking tile.
+    /// Each axis is resolved separately, so that a box moving against a wall slides along it
+    pub fn slide(&self, center: Vec2, half_size: Vec2, delta: Vec2) -> Vec2 {
+        let x = self.sweep(center - half_size, center + half_size, delta.x, 0);
+        let center = center + Vec2::new(x, 0.0);
+        let y = self.sweep(center - half_size, center + half_size, delta.y, 1);
+        Vec2::new(x, y)
+    }
+    /// Distance the box from `min` to `max` can travel along `axis`, at most `distance`
+    fn sweep(&self, min: Vec2, max: Vec2, distance: f32, axis: usize) -> f32 {
+        if distance == 0.0 {
+            return 0.0;
+        }
+        // Only the area swept by the leading edge of the box is checked
+        let (mut from, mut to) = (min, max);
+        if distance > 0.0 {
+            from[axis] = max[axis];
+            to[axis] = max[axis] + distance;
+        } else {
+            from[axis] = min[axis] + distance;
+            to[axis] = min[axis];
+        }
+        let start = self.tile_at(from + EDGE_EPSILON);
+        let end = self.tile_at(to - EDGE_EPSILON);
+        let mut allowed = distance;
+        for y in start.y..=end.y {
+            for x in start.x..=end.x {
+                let tile = IVec2::new(x, y);
+                if !self.get(tile).blocks_movement() {
+                    continue;
+                }
+                let tile_min = self.origin + tile.as_vec2() * TILE_SIZE;
+                allowed = if distance > 0.0 {
+                    allowed.min(tile_min[axis] - max[axis])
+                } else {
+                    allowed.max(tile_min[axis] + TILE_SIZE - min[axis])
+                };
+            }
+        }
+        // A box already stuck in a tile can only move out of it
+        if distance > 0.0 {
+            allowed.max(0.0)
+        } else {
+            allowed.min(0.0)
+        }
+    }
+}
+
+#[derive(Component)]
+/// Drawn tile, with the chunk it belongs to
+pub struct TileSprite(IVec2);
+
+/// Draws the tiles of the loaded chunks, and only them. Everything is redrawn when the map changes
+fn stream_tile_sprites(
+    mut commands: Commands,
+    map: Res<TileMap>,
+    chunks: Res<ChunkManager>,
+    q_sprites: Query<(Entity, &TileSprite)>,
+    mut drawn: Local<HashSet<IVec2>>,
+) {
+    if !map.is_changed() && !chunks.is_changed() {
+        return;
+    }
+    for (entity, sprite) in q_sprites.iter() {
+        if map.is_changed() || !chunks.is_loaded(sprite.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    if map.is_changed() {
+        drawn.clear();
+    }
+    drawn.retain(|chunk| chunks.is_loaded(*chunk));
+    for chunk in chunks.loaded_chunks() {
+        if !drawn.insert(chunk) {
+            continue;
+        }
+        let (min, max) = ChunkManager::chunk_tiles(&map, chunk);
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let tile = IVec2::new(x, y);
+                if let Some(color) = map.get(tile).color() {
+                    spawn_tile_sprite(&mut commands, &map, tile, chunk, color);
+                }
+            }
+        }
+    }
+}
+
+fn spawn_tile_sprite(
+    commands: &mut Commands,
+    map: &TileMap,
+    tile: IVec2,
+    chunk: IVec2,
+    color: Color,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(TILE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(map.tile_center(tile).extend(TILE_Z)),
+            ..default()
+        },
+        TileSprite(chunk),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_outside_are_floor() {
+        let map = TileMap::from_ascii(&["#.", ".~"]);
+        assert_eq!(map.get(IVec2::new(0, 1)), TileKind::Wall);
+        assert_eq!(map.get(IVec2::new(1, 0)), TileKind::Water);
+        assert_eq!(map.get(IVec2::new(5, 5)), TileKind::Floor);
+    }
+
+    #[test]
+    fn movement_stops_at_walls() {
+        let map = TileMap::from_ascii(&["..#"]);
+        let half_size = Vec2::splat(10.0);
+        let center = Vec2::new(TILE_SIZE * 1.5, TILE_SIZE * 0.5);
+        let delta = map.slide(center, half_size, Vec2::new(TILE_SIZE, 0.0));
+        assert!((center.x + delta.x + half_size.x - TILE_SIZE * 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn movement_slides_along_walls() {
+        let map = TileMap::from_ascii(&["..", "..", "#."]);
+        let half_size = Vec2::splat(10.0);
+        // Standing right above the wall, moving down and to the right
+        let center = Vec2::new(TILE_SIZE * 0.5, TILE_SIZE + half_size.y);
+        let delta = map.slide(center, half_size, Vec2::new(5.0, -5.0));
+        assert_eq!(delta, Vec2::new(5.0, 0.0));
+    }
+}